@@ -0,0 +1,309 @@
+//! An in-process stand-in for the UpCloud API, for testing code built on
+//! `cloudup` without network access or credentials.
+//!
+//! ```no_run
+//! # async fn example() {
+//! let mock = cloudup_mock::MockUpCloud::start().await;
+//! // Point a cloudup::Context at mock.uri() using mock.username() and mock.password().
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+mod routes;
+pub mod state;
+
+use routes::Reply;
+pub use state::State;
+
+/// A failure to answer with instead of the regular response.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    method: Option<String>,
+    path: Option<String>,
+    status: u16,
+    code: String,
+    message: String,
+    times: usize,
+    applied: bool,
+    delay: Duration,
+}
+
+impl Failure {
+    /// Fails the next request, whatever it is, with `status` and UpCloud error `code`.
+    pub fn new(status: u16, code: &str) -> Failure {
+        Failure {
+            method: None,
+            path: None,
+            status,
+            code: String::from(code),
+            message: String::from("Injected by cloudup-mock."),
+            times: 1,
+            applied: false,
+            delay: Duration::from_secs(0),
+        }
+    }
+
+    /// Only fails requests with this method, e.g. `POST`.
+    pub fn method(mut self, method: &str) -> Failure {
+        self.method = Some(method.to_uppercase());
+        self
+    }
+
+    /// Only fails requests whose path, relative to `/1.3`, starts with `path`.
+    /// Uploads to an import's `direct_upload_url` are outside the API and
+    /// matched by their full path, e.g. `/uploader/session/`.
+    pub fn path(mut self, path: &str) -> Failure {
+        self.path = Some(String::from(path));
+        self
+    }
+
+    pub fn message(mut self, message: &str) -> Failure {
+        self.message = String::from(message);
+        self
+    }
+
+    /// Fails this many matching requests instead of one.
+    pub fn times(mut self, times: usize) -> Failure {
+        self.times = times;
+        self
+    }
+
+    /// Handles the request as usual before failing, as when UpCloud did what
+    /// was asked but the response got lost.
+    pub fn after_applying(mut self) -> Failure {
+        self.applied = true;
+        self
+    }
+
+    /// Waits this long before answering, e.g. to run into the client's timeout.
+    pub fn delay(mut self, delay: Duration) -> Failure {
+        self.delay = delay;
+        self
+    }
+
+    fn matches(&self, method: &str, path: &str) -> bool {
+        self.method.iter().all(|m| m == method)
+            && self.path.iter().all(|p| path.starts_with(p.as_str()))
+    }
+}
+
+/// A request the mock has received.
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: String,
+    /// Path relative to `/1.3`, without the query string.
+    pub path: String,
+    pub query: String,
+    pub body: Option<Value>,
+}
+
+struct Shared {
+    username: String,
+    password: String,
+    state: Mutex<State>,
+    failures: Mutex<VecDeque<Failure>>,
+    latency: Mutex<Duration>,
+    received: Mutex<Vec<ReceivedRequest>>,
+}
+
+/// A running mock API. Shuts down when dropped.
+pub struct MockUpCloud {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockUpCloud {
+    /// Starts a mock accepting the credentials `user`/`pass`.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub async fn start() -> MockUpCloud {
+        MockUpCloud::with_credentials("user", "pass").await
+    }
+
+    pub async fn with_credentials(username: &str, password: &str) -> MockUpCloud {
+        let shared = Arc::new(Shared {
+            username: String::from(username),
+            password: String::from(password),
+            state: Mutex::new(State::new(username)),
+            failures: Mutex::new(VecDeque::new()),
+            latency: Mutex::new(Duration::from_secs(0)),
+            received: Mutex::new(vec![]),
+        });
+
+        let service_shared = shared.clone();
+        let make_service = make_service_fn(move |_| {
+            let shared = service_shared.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| handle(shared.clone(), request)))
+            }
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let address = server.local_addr();
+        shared.state.lock().unwrap().base_url = format!("http://{}", address);
+        let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            shutdown_signal.await.ok();
+        }));
+
+        MockUpCloud {
+            address,
+            shared,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// The base URL to point `cloudup` at.
+    pub fn uri(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    pub fn username(&self) -> String {
+        self.shared.username.clone()
+    }
+
+    pub fn password(&self) -> String {
+        self.shared.password.clone()
+    }
+
+    /// The mock's data, for seeding it or checking what a test did to it.
+    pub fn state(&self) -> MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap()
+    }
+
+    /// Queues up a failure. Failures are used in the order they were added.
+    pub fn fail(&self, failure: Failure) {
+        self.shared.failures.lock().unwrap().push_back(failure);
+    }
+
+    /// Delays every response by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        *self.shared.latency.lock().unwrap() = latency;
+    }
+
+    /// Keeps servers in `maintenance` for `delay` whenever they are created,
+    /// started, stopped or restarted.
+    pub fn set_transition_delay(&self, delay: Duration) {
+        self.state().transition_delay = delay;
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn received_requests(&self) -> Vec<ReceivedRequest> {
+        self.shared.received.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockUpCloud {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+fn is_authorized(shared: &Shared, request: &Request<Body>) -> bool {
+    let expected = format!(
+        "Basic {}",
+        base64::encode(format!("{}:{}", shared.username, shared.password))
+    );
+    matches!(
+        request.headers().get(hyper::header::AUTHORIZATION),
+        Some(value) if value.as_bytes() == expected.as_bytes()
+    )
+}
+
+fn take_failure(shared: &Shared, method: &str, path: &str) -> Option<Failure> {
+    let mut failures = shared.failures.lock().unwrap();
+    let position = failures
+        .iter()
+        .position(|failure| failure.matches(method, path))?;
+    let failure = failures[position].clone();
+    if failures[position].times > 1 {
+        failures[position].times -= 1;
+    } else {
+        failures.remove(position);
+    }
+    Some(failure)
+}
+
+async fn handle(shared: Arc<Shared>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let authorized = is_authorized(&shared, &request);
+    let method = request.method().as_str().to_string();
+    let full_path = request.uri().path().to_string();
+    let query = request.uri().query().unwrap_or_default().to_string();
+    let content_type = request
+        .headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let bytes = hyper::body::to_bytes(request.into_body())
+        .await
+        .unwrap_or_default();
+    let body: Option<Value> = serde_json::from_slice(&bytes).ok();
+
+    let latency = *shared.latency.lock().unwrap();
+    if latency > Duration::from_secs(0) {
+        tokio::time::sleep(latency).await;
+    }
+
+    let upload_session = full_path.strip_prefix("/uploader/session/");
+    let reply = match full_path.strip_prefix("/1.3") {
+        // Storage import uploads don't go through the API.
+        None if method == "PUT" && upload_session.is_some() => {
+            if let Some(failure) = take_failure(&shared, &method, &full_path) {
+                tokio::time::sleep(failure.delay).await;
+                Reply::error(failure.status, &failure.code, &failure.message)
+            } else {
+                let mut state = shared.state.lock().unwrap();
+                routes::receive_upload(&mut state, upload_session.unwrap(), &content_type, &bytes)
+            }
+        }
+        None => Reply::error(404, "NOT_FOUND", "Only API version 1.3 is mocked."),
+        Some(path) => {
+            shared.received.lock().unwrap().push(ReceivedRequest {
+                method: method.clone(),
+                path: String::from(path),
+                query: query.clone(),
+                body: body.clone(),
+            });
+            if !authorized {
+                Reply::error(
+                    401,
+                    "AUTHENTICATION_FAILED",
+                    "Authentication failed using the given username and password.",
+                )
+            } else if let Some(failure) = take_failure(&shared, &method, path) {
+                if failure.applied {
+                    let mut state = shared.state.lock().unwrap();
+                    routes::route(&mut state, &method, path, &query, body);
+                }
+                tokio::time::sleep(failure.delay).await;
+                Reply::error(failure.status, &failure.code, &failure.message)
+            } else {
+                let mut state = shared.state.lock().unwrap();
+                routes::route(&mut state, &method, path, &query, body)
+            }
+        }
+    };
+
+    let mut response = Response::builder().status(reply.status);
+    let body = match reply.body {
+        Some(json) => {
+            response = response.header(hyper::header::CONTENT_TYPE, "application/json");
+            Body::from(json.to_string())
+        }
+        None => Body::empty(),
+    };
+    Ok(response.body(body).unwrap())
+}
@@ -0,0 +1,2180 @@
+use std::io::Read;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::state::{
+    account_details, now, rfc3339, DetachedIpAddress, Interface, IpAddress, Network, Router,
+    Server, State, Storage, StorageDevice,
+};
+
+/// What the mock answers with.
+#[derive(Debug)]
+pub(crate) struct Reply {
+    pub(crate) status: u16,
+    pub(crate) body: Option<Value>,
+}
+
+impl Reply {
+    pub(crate) fn json(status: u16, body: Value) -> Reply {
+        Reply {
+            status,
+            body: Some(body),
+        }
+    }
+
+    pub(crate) fn no_content() -> Reply {
+        Reply {
+            status: 204,
+            body: None,
+        }
+    }
+
+    pub(crate) fn error(status: u16, code: &str, message: &str) -> Reply {
+        Reply::json(
+            status,
+            json!({ "error": { "error_code": code, "error_message": message } }),
+        )
+    }
+}
+
+/// Handles a request to `path`, given relative to `/1.3`.
+pub(crate) fn route(
+    state: &mut State,
+    method: &str,
+    path: &str,
+    query: &str,
+    body: Option<Value>,
+) -> Reply {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["account"]) => Reply::json(200, json!({ "account": state.account })),
+        ("GET", ["account", "list"]) => list_accounts(state),
+        ("GET", ["account", "details", username]) => get_account(state, username),
+        ("PUT", ["account", "details", username]) => modify_account(state, username, body),
+        ("POST", ["account", "sub"]) => create_sub_account(state, body),
+        ("DELETE", ["account", "sub", username]) => delete_sub_account(state, username),
+        ("GET", ["permission"]) => Reply::json(
+            200,
+            json!({ "permissions": { "permission": state.permissions } }),
+        ),
+        ("POST", ["permission", "grant"]) => grant_permission(state, body),
+        ("POST", ["permission", "revoke"]) => revoke_permission(state, body),
+        ("GET", ["plan"]) => Reply::json(200, json!({ "plans": { "plan": state.plans } })),
+        ("GET", ["price"]) => Reply::json(200, json!({ "prices": { "zone": state.prices } })),
+        ("GET", ["zone"]) => Reply::json(200, json!({ "zones": { "zone": state.zones } })),
+
+        ("GET", ["server"]) => list_servers(state),
+        ("POST", ["server"]) => create_server(state, body),
+        ("GET", ["server", uuid]) => get_server(state, uuid),
+        ("DELETE", ["server", uuid]) => delete_server(state, uuid, query),
+        ("POST", ["server", uuid, "start"]) => change_state(state, uuid, "stopped", "started"),
+        ("POST", ["server", uuid, "stop"]) => change_state(state, uuid, "started", "stopped"),
+        ("POST", ["server", uuid, "restart"]) => change_state(state, uuid, "started", "started"),
+        ("POST", ["server", uuid, "cancel"]) => cancel_operation(state, uuid),
+        ("PUT", ["server", uuid]) => modify_server(state, uuid, body),
+        ("POST", ["server", uuid, "cdrom", "load"]) => load_cdrom(state, uuid, body),
+        ("POST", ["server", uuid, "cdrom", "eject"]) => eject_cdrom(state, uuid),
+        ("GET", ["server", uuid, "firewall_rule"]) => list_firewall_rules(state, uuid),
+        ("POST", ["server", uuid, "firewall_rule"]) => create_firewall_rule(state, uuid, body),
+        ("PUT", ["server", uuid, "firewall_rule"]) => replace_firewall_rules(state, uuid, body),
+        ("GET", ["server", uuid, "firewall_rule", position]) => {
+            get_firewall_rule(state, uuid, position)
+        }
+        ("DELETE", ["server", uuid, "firewall_rule", position]) => {
+            delete_firewall_rule(state, uuid, position)
+        }
+
+        ("GET", ["storage"]) => list_storages(state, |_| true),
+        ("GET", ["storage", access @ ("public" | "private")]) => {
+            list_storages(state, |storage| storage.access == *access)
+        }
+        ("GET", ["storage", kind @ ("normal" | "backup" | "cdrom" | "template")]) => {
+            list_storages(state, |storage| storage.storage_type == *kind)
+        }
+        // Nothing can be marked as a favorite in the mock.
+        ("GET", ["storage", "favorite"]) => list_storages(state, |_| false),
+        ("POST", ["storage"]) => create_storage(state, body),
+        ("GET", ["storage", uuid]) => get_storage(state, uuid),
+        ("PUT", ["storage", uuid]) => modify_storage(state, uuid, body),
+        ("DELETE", ["storage", uuid]) => delete_storage(state, uuid),
+        ("POST", ["storage", uuid, "clone"]) => clone_storage(state, uuid, body),
+        ("POST", ["storage", uuid, "backup"]) => backup_storage(state, uuid, body),
+        ("POST", ["storage", uuid, "restore"]) => restore_backup(state, uuid),
+        ("POST", ["storage", uuid, "templatize"]) => templatize_storage(state, uuid, body),
+        ("POST", ["storage", uuid, "resize"]) => resize_storage(state, uuid),
+        ("POST", ["storage", uuid, "import"]) => start_import(state, uuid, body),
+        ("GET", ["storage", uuid, "import"]) => get_import(state, uuid),
+        ("POST", ["storage", uuid, "import", "cancel"]) => cancel_import(state, uuid),
+        ("POST", ["server", uuid, "storage", "attach"]) => attach_storage(state, uuid, body),
+        ("POST", ["server", uuid, "storage", "detach"]) => detach_storage(state, uuid, body),
+
+        ("GET", ["server", uuid, "networking"]) => list_interfaces(state, uuid),
+        ("GET", ["server", uuid, "networking", "interface", index]) => {
+            get_interface(state, uuid, index)
+        }
+        ("POST", ["server", uuid, "networking", "interface"]) => add_interface(state, uuid, body),
+        ("PUT", ["server", uuid, "networking", "interface", index]) => {
+            modify_interface(state, uuid, index, body)
+        }
+        ("DELETE", ["server", uuid, "networking", "interface", index]) => {
+            delete_interface(state, uuid, index)
+        }
+
+        ("GET", ["network"]) => list_networks(state, query),
+        ("POST", ["network"]) => create_network(state, body),
+        ("GET", ["network", uuid]) => network_details(state, uuid, 200),
+        ("PUT", ["network", uuid]) => modify_network(state, uuid, body),
+        ("DELETE", ["network", uuid]) => delete_network(state, uuid),
+        ("GET", ["router"]) => list_routers(state),
+        ("POST", ["router"]) => create_router(state, body),
+        ("GET", ["router", uuid]) => router_details(state, uuid, 200),
+        ("DELETE", ["router", uuid]) => delete_router(state, uuid),
+
+        ("GET", ["ip_address"]) => list_ip_addresses(state),
+        ("POST", ["ip_address"]) => assign_ip_address(state, body),
+        ("GET", ["ip_address", address]) => get_ip_address(state, address),
+        ("PUT", ["ip_address", address]) => modify_ip_address(state, address, body),
+        ("DELETE", ["ip_address", address]) => release_ip_address(state, address),
+
+        _ => Reply::error(
+            404,
+            "NOT_FOUND",
+            "No such endpoint in the mock UpCloud API.",
+        ),
+    }
+}
+
+fn account_not_found(username: &str) -> Reply {
+    Reply::error(
+        404,
+        "ACCOUNT_NOT_FOUND",
+        &format!("The account {} does not exist.", username),
+    )
+}
+
+fn list_accounts(state: &State) -> Reply {
+    let accounts: Vec<Value> = state
+        .accounts
+        .values()
+        .map(|details| {
+            json!({
+                "roles": details["roles"],
+                "type": details["type"],
+                "username": details["username"],
+            })
+        })
+        .collect();
+    Reply::json(200, json!({ "accounts": { "account": accounts } }))
+}
+
+fn get_account(state: &State, username: &str) -> Reply {
+    match state.accounts.get(username) {
+        Some(details) => Reply::json(200, json!({ "account": details })),
+        None => account_not_found(username),
+    }
+}
+
+/// Copies the settings UpCloud lets an account change from `changes` to `details`.
+fn apply_account_changes(details: &mut Value, changes: &Value) {
+    let fixed = ["main_account", "type", "username", "password", "campaigns"];
+    if let (Some(details), Some(changes)) = (details.as_object_mut(), changes.as_object()) {
+        for (key, value) in changes {
+            if !fixed.contains(&key.as_str()) && details.contains_key(key) {
+                details.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn create_sub_account(state: &mut State, body: Option<Value>) -> Reply {
+    let changes = match body.as_ref().and_then(|body| body.get("sub_account")) {
+        Some(changes) => changes,
+        None => return Reply::error(400, "ACTION_INVALID", "Missing sub_account."),
+    };
+    let username = match str_field(changes, "/username") {
+        Some(username) => String::from(username),
+        None => return Reply::error(400, "USERNAME_MISSING", "The username is missing."),
+    };
+    if str_field(changes, "/password").is_none() {
+        return Reply::error(400, "PASSWORD_MISSING", "The password is missing.");
+    }
+    if state.accounts.contains_key(&username) {
+        return Reply::error(
+            409,
+            "USERNAME_EXISTS",
+            &format!("The username {} is already in use.", username),
+        );
+    }
+    let main_account = state.account["username"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let mut details = account_details(&username, &main_account, "sub");
+    apply_account_changes(&mut details, changes);
+    state.accounts.insert(username, details.clone());
+    Reply::json(201, json!({ "account": details }))
+}
+
+fn modify_account(state: &mut State, username: &str, body: Option<Value>) -> Reply {
+    let details = match state.accounts.get_mut(username) {
+        Some(details) => details,
+        None => return account_not_found(username),
+    };
+    if let Some(changes) = body.as_ref().and_then(|body| body.get("account")) {
+        apply_account_changes(details, changes);
+    }
+    Reply::json(202, json!({ "account": details }))
+}
+
+fn delete_sub_account(state: &mut State, username: &str) -> Reply {
+    match state.accounts.get(username) {
+        None => account_not_found(username),
+        Some(details) if details["type"] != "sub" => Reply::error(
+            403,
+            "ACCOUNT_FORBIDDEN",
+            "Only sub-accounts can be deleted.",
+        ),
+        Some(_) => {
+            state.accounts.remove(username);
+            Reply::no_content()
+        }
+    }
+}
+
+/// The permission in a grant or revoke request, with its options filled in.
+fn permission_field(body: Option<Value>) -> Option<Value> {
+    let mut permission = body?.get("permission")?.clone();
+    if permission.get("options").is_none() {
+        permission["options"] = json!({});
+    }
+    Some(permission)
+}
+
+fn grant_permission(state: &mut State, body: Option<Value>) -> Reply {
+    let permission = match permission_field(body) {
+        Some(permission) => permission,
+        None => return Reply::error(400, "ACTION_INVALID", "Missing permission."),
+    };
+    let user = str_field(&permission, "/user").unwrap_or_default();
+    match state.accounts.get(user) {
+        Some(details) if details["type"] == "sub" => {}
+        Some(_) => {
+            return Reply::error(
+                403,
+                "ACCOUNT_FORBIDDEN",
+                "Permissions can only be granted to sub-accounts.",
+            )
+        }
+        None => return account_not_found(user),
+    }
+    let same_target = |granted: &Value| {
+        granted["user"] == permission["user"]
+            && granted["target_type"] == permission["target_type"]
+            && granted["target_identifier"] == permission["target_identifier"]
+    };
+    if state.permissions.iter().any(same_target) {
+        return Reply::error(409, "PERMISSION_EXISTS", "The permission already exists.");
+    }
+    state.permissions.push(permission.clone());
+    Reply::json(201, json!({ "permission": permission }))
+}
+
+fn revoke_permission(state: &mut State, body: Option<Value>) -> Reply {
+    let permission = match permission_field(body) {
+        Some(permission) => permission,
+        None => return Reply::error(400, "ACTION_INVALID", "Missing permission."),
+    };
+    let count = state.permissions.len();
+    state.permissions.retain(|granted| {
+        granted["user"] != permission["user"]
+            || granted["target_type"] != permission["target_type"]
+            || granted["target_identifier"] != permission["target_identifier"]
+    });
+    if state.permissions.len() == count {
+        return Reply::error(404, "PERMISSION_NOT_FOUND", "No such permission.");
+    }
+    Reply::no_content()
+}
+
+fn server_not_found(uuid: &str) -> Reply {
+    Reply::error(
+        404,
+        "SERVER_NOT_FOUND",
+        &format!("The server {} does not exist.", uuid),
+    )
+}
+
+fn storage_not_found(uuid: &str) -> Reply {
+    Reply::error(
+        404,
+        "STORAGE_NOT_FOUND",
+        &format!("The storage {} does not exist.", uuid),
+    )
+}
+
+fn server_state_illegal(server: &Server) -> Reply {
+    Reply::error(
+        400,
+        "SERVER_STATE_ILLEGAL",
+        &format!("The server {} is {}.", server.uuid, server.state),
+    )
+}
+
+pub(crate) fn server_details(state: &mut State, uuid: &str, status: u16) -> Reply {
+    match state.server(uuid) {
+        Some(_) => Reply::json(
+            status,
+            json!({ "server": state.servers[uuid].to_details_json(&state.storages) }),
+        ),
+        None => server_not_found(uuid),
+    }
+}
+
+fn list_servers(state: &mut State) -> Reply {
+    let uuids: Vec<String> = state.servers.keys().cloned().collect();
+    let servers: Vec<Value> = uuids
+        .iter()
+        .filter_map(|uuid| state.server(uuid).map(|server| server.to_list_json()))
+        .collect();
+    Reply::json(200, json!({ "servers": { "server": servers } }))
+}
+
+fn get_server(state: &mut State, uuid: &str) -> Reply {
+    server_details(state, uuid, 200)
+}
+
+fn str_field<'a>(value: &'a Value, pointer: &str) -> Option<&'a str> {
+    value.pointer(pointer).and_then(Value::as_str)
+}
+
+fn number_field(value: &Value, pointer: &str) -> Option<u64> {
+    match value.pointer(pointer)? {
+        Value::Number(number) => number.as_u64(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn allocate_address(
+    state: &mut State,
+    interface_type: &str,
+    family: &str,
+    requested: Option<&str>,
+) -> String {
+    if let Some(address) = requested {
+        return String::from(address);
+    }
+    let n = state.next_number();
+    match (interface_type, family) {
+        (_, "IPv6") => format!("2a04:3540:1000:310::{:x}", n),
+        ("public", _) => format!("94.237.{}.{}", n / 256 % 256, n % 256),
+        ("utility", _) => format!("10.3.{}.{}", n / 256 % 256, n % 256),
+        _ => format!("10.0.{}.{}", n / 256 % 256, n % 256),
+    }
+}
+
+/// The reverse DNS name UpCloud gives public addresses, e.g.
+/// `94-237-0-5.fi-hel1.upcloud.host`.
+fn default_ptr_record(interface_type: &str, address: &str, zone: &str) -> String {
+    if interface_type != "public" {
+        return String::new();
+    }
+    format!("{}.{}.upcloud.host", address.replace(['.', ':'], "-"), zone)
+}
+
+pub(crate) fn build_interface(
+    state: &mut State,
+    zone: &str,
+    index: u32,
+    spec: &Value,
+) -> Interface {
+    let interface_type = str_field(spec, "/type").unwrap_or("public").to_string();
+    let network = match str_field(spec, "/network") {
+        Some(network) => String::from(network),
+        None => format!("03000000-0000-4000-8000-{:012x}", state.next_number()),
+    };
+    let specs: Vec<Value> = spec
+        .pointer("/ip_addresses/ip_address")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let ip_addresses = specs
+        .iter()
+        .map(|ip| {
+            let family = str_field(ip, "/family").unwrap_or("IPv4").to_string();
+            let address =
+                allocate_address(state, &interface_type, &family, str_field(ip, "/address"));
+            IpAddress {
+                ptr_record: default_ptr_record(&interface_type, &address, zone),
+                address,
+                family,
+                floating: false,
+            }
+        })
+        .collect();
+    let mac_number = state.next_number();
+    Interface {
+        index,
+        interface_type,
+        network,
+        mac: format!(
+            "de:ff:ff:ff:{:02x}:{:02x}",
+            mac_number / 256 % 256,
+            mac_number % 256
+        ),
+        ip_addresses,
+        source_ip_filtering: str_field(spec, "/source_ip_filtering") != Some("no"),
+        bootable: str_field(spec, "/bootable") == Some("yes"),
+    }
+}
+
+fn create_server(state: &mut State, body: Option<Value>) -> Reply {
+    let body = match body {
+        Some(body) => body,
+        None => return Reply::error(400, "ACTION_INVALID", "Missing request body."),
+    };
+    let zone = str_field(&body, "/server/zone")
+        .unwrap_or_default()
+        .to_string();
+    if !state.zones.iter().any(|z| z["id"] == zone.as_str()) {
+        return Reply::error(400, "ZONE_INVALID", "The zone is invalid.");
+    }
+    let title = match str_field(&body, "/server/title") {
+        Some(title) => String::from(title),
+        None => return Reply::error(400, "TITLE_MISSING", "The title is missing."),
+    };
+    let hostname = str_field(&body, "/server/hostname")
+        .unwrap_or_default()
+        .to_string();
+
+    let plan_name = str_field(&body, "/server/plan")
+        .unwrap_or("custom")
+        .to_string();
+    let (core_number, memory_amount) = if plan_name == "custom" {
+        (
+            number_field(&body, "/server/core_number").unwrap_or(1) as u32,
+            number_field(&body, "/server/memory_amount").unwrap_or(1024),
+        )
+    } else {
+        match state
+            .plans
+            .iter()
+            .find(|plan| plan["name"] == plan_name.as_str())
+        {
+            Some(plan) => (
+                plan["core_number"].as_u64().unwrap_or(1) as u32,
+                plan["memory_amount"].as_u64().unwrap_or(1024),
+            ),
+            None => return Reply::error(400, "PLAN_INVALID", "The plan is invalid."),
+        }
+    };
+
+    let uuid = state.next_uuid();
+
+    let devices: Vec<Value> = body
+        .pointer("/server/storage_devices/storage_device")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let mut storage_devices = vec![];
+    for (position, device) in devices.iter().enumerate() {
+        let action = str_field(device, "/action").unwrap_or("create");
+        let device_type = str_field(device, "/type").unwrap_or("disk").to_string();
+        let storage = match action {
+            "attach" => {
+                let source = str_field(device, "/storage").unwrap_or_default();
+                match state.storages.get_mut(source) {
+                    Some(storage) => {
+                        storage.servers.push(uuid.clone());
+                        storage.uuid.clone()
+                    }
+                    None => return storage_not_found(source),
+                }
+            }
+            "clone" | "create" => {
+                let origin = match action {
+                    "clone" => {
+                        let source = str_field(device, "/storage").unwrap_or_default();
+                        match state.storages.get(source) {
+                            Some(storage) => Some(storage.clone()),
+                            None => return storage_not_found(source),
+                        }
+                    }
+                    _ => None,
+                };
+                let storage_uuid = state.next_uuid();
+                let size = number_field(device, "/size")
+                    .or_else(|| origin.as_ref().map(|origin| origin.size))
+                    .unwrap_or(10);
+                state.storages.insert(
+                    storage_uuid.clone(),
+                    Storage {
+                        uuid: storage_uuid.clone(),
+                        title: str_field(device, "/title")
+                            .map(String::from)
+                            .unwrap_or_else(|| format!("{}-disk{}", hostname, position)),
+                        size,
+                        tier: str_field(device, "/tier").unwrap_or("maxiops").to_string(),
+                        zone: zone.clone(),
+                        access: String::from("private"),
+                        storage_type: String::from("normal"),
+                        state: String::from("online"),
+                        license: 0,
+                        created: now(),
+                        origin: origin.map(|origin| origin.uuid),
+                        servers: vec![uuid.clone()],
+                        backup_rule: None,
+                        backups: vec![],
+                        busy_until: None,
+                    },
+                );
+                storage_uuid
+            }
+            _ => return Reply::error(400, "ACTION_INVALID", "Unknown storage action."),
+        };
+        storage_devices.push(StorageDevice {
+            address: format!("virtio:{}", position),
+            storage,
+            device_type,
+        });
+    }
+
+    let specs: Vec<Value> = body
+        .pointer("/server/networking/interfaces/interface")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let interfaces = specs
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| build_interface(state, &zone, index as u32 + 1, spec))
+        .collect();
+
+    let tags = body
+        .pointer("/server/tags/tag")
+        .and_then(Value::as_array)
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| tag.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut server = Server {
+        uuid: uuid.clone(),
+        title,
+        hostname,
+        zone,
+        plan: plan_name,
+        core_number,
+        memory_amount,
+        state: String::from("stopped"),
+        pending: None,
+        host: 7653311107,
+        created: now(),
+        boot_order: str_field(&body, "/server/boot_order")
+            .unwrap_or("disk")
+            .to_string(),
+        firewall: str_field(&body, "/server/firewall") == Some("on"),
+        metadata: str_field(&body, "/server/metadata") == Some("yes"),
+        timezone: str_field(&body, "/server/timezone")
+            .unwrap_or("UTC")
+            .to_string(),
+        simple_backup: String::from("no"),
+        tags,
+        storage_devices,
+        interfaces,
+        firewall_rules: vec![],
+    };
+    server.transition("started", state.transition_delay);
+    state.servers.insert(uuid.clone(), server);
+
+    server_details(state, &uuid, 202)
+}
+
+fn delete_server(state: &mut State, uuid: &str, query: &str) -> Reply {
+    let server = match state.server(uuid) {
+        Some(server) => server,
+        None => return server_not_found(uuid),
+    };
+    if server.state != "stopped" {
+        return server_state_illegal(server);
+    }
+    let server = state.servers.remove(uuid).unwrap_or_else(|| unreachable!());
+    let with_storages = query.split('&').any(|pair| pair == "storages=1");
+    for device in server.storage_devices {
+        if let Some(storage) = state.storages.get_mut(&device.storage) {
+            storage.servers.retain(|attached| attached != uuid);
+            if with_storages && storage.access == "private" {
+                state.storages.remove(&device.storage);
+            }
+        }
+    }
+    // Floating addresses stay on the account.
+    for interface in server.interfaces {
+        for ip in interface.ip_addresses.into_iter().filter(|ip| ip.floating) {
+            let zone = server.zone.clone();
+            state
+                .detached_ips
+                .insert(ip.address.clone(), DetachedIpAddress { ip, zone });
+        }
+    }
+    Reply::no_content()
+}
+
+fn change_state(state: &mut State, uuid: &str, from: &str, to: &str) -> Reply {
+    let delay = state.transition_delay;
+    let server = match state.server(uuid) {
+        Some(server) => server,
+        None => return server_not_found(uuid),
+    };
+    if server.state != from {
+        return server_state_illegal(server);
+    }
+    server.transition(to, delay);
+    server_details(state, uuid, 200)
+}
+
+fn cancel_operation(state: &mut State, uuid: &str) -> Reply {
+    let server = match state.server(uuid) {
+        Some(server) => server,
+        None => return server_not_found(uuid),
+    };
+    if server.pending.is_none() {
+        return server_state_illegal(server);
+    }
+    server.pending = None;
+    server.state = String::from("started");
+    server_details(state, uuid, 200)
+}
+
+fn list_storages<F: Fn(&Storage) -> bool>(state: &State, filter: F) -> Reply {
+    let storages: Vec<Value> = state
+        .storages
+        .values()
+        .filter(|storage| filter(storage))
+        .map(Storage::to_list_json)
+        .collect();
+    Reply::json(200, json!({ "storages": { "storage": storages } }))
+}
+
+fn delete_storage(state: &mut State, uuid: &str) -> Reply {
+    match state.storages.get(uuid) {
+        None => storage_not_found(uuid),
+        Some(storage) if !storage.servers.is_empty() => Reply::error(
+            409,
+            "STORAGE_IN_USE",
+            &format!("The storage {} is attached to a server.", uuid),
+        ),
+        Some(_) => {
+            let storage = state.storages.remove(uuid).unwrap();
+            if let Some(origin) = storage.origin.and_then(|o| state.storages.get_mut(&o)) {
+                origin.backups.retain(|backup| backup != uuid);
+            }
+            Reply::no_content()
+        }
+    }
+}
+
+fn backup_storage(state: &mut State, uuid: &str, body: Option<Value>) -> Reply {
+    let source = match state.storages.get(uuid) {
+        Some(source) if source.storage_type == "normal" => source.clone(),
+        Some(_) => {
+            return Reply::error(
+                400,
+                "STORAGE_TYPE_ILLEGAL",
+                "Only normal storages can be backed up.",
+            )
+        }
+        None => return storage_not_found(uuid),
+    };
+    let title = match body
+        .as_ref()
+        .and_then(|body| str_field(body, "/storage/title"))
+    {
+        Some(title) => String::from(title),
+        None => return Reply::error(400, "TITLE_MISSING", "The title is missing."),
+    };
+    let backup_uuid = state.next_uuid();
+    state.storages.insert(
+        backup_uuid.clone(),
+        Storage {
+            uuid: backup_uuid.clone(),
+            title,
+            size: source.size,
+            tier: source.tier,
+            zone: source.zone,
+            access: String::from("private"),
+            storage_type: String::from("backup"),
+            state: String::from("online"),
+            license: source.license,
+            created: now(),
+            origin: Some(source.uuid),
+            servers: vec![],
+            backup_rule: None,
+            backups: vec![],
+            busy_until: None,
+        },
+    );
+    state
+        .storages
+        .get_mut(uuid)
+        .unwrap()
+        .backups
+        .push(backup_uuid.clone());
+    storage_details(state, &backup_uuid, 201)
+}
+
+fn restore_backup(state: &mut State, uuid: &str) -> Reply {
+    let origin = match state.storages.get(uuid) {
+        Some(backup) if backup.storage_type == "backup" => backup.origin.clone(),
+        _ => {
+            return Reply::error(
+                404,
+                "BACKUP_NOT_FOUND",
+                &format!("The backup {} does not exist.", uuid),
+            )
+        }
+    };
+    let servers = match origin.as_ref().and_then(|o| state.storages.get(o)) {
+        Some(origin) => origin.servers.clone(),
+        None => return storage_not_found(origin.as_deref().unwrap_or_default()),
+    };
+    for server in servers {
+        if matches!(state.server(&server), Some(s) if s.state != "stopped") {
+            return Reply::error(
+                400,
+                "SERVER_STATE_ILLEGAL",
+                &format!("The storage is attached to the running server {}.", server),
+            );
+        }
+    }
+    Reply::no_content()
+}
+
+fn storage_details(state: &State, uuid: &str, status: u16) -> Reply {
+    match state.storages.get(uuid) {
+        Some(storage) => Reply::json(status, json!({ "storage": storage.to_details_json() })),
+        None => storage_not_found(uuid),
+    }
+}
+
+fn get_storage(state: &State, uuid: &str) -> Reply {
+    storage_details(state, uuid, 200)
+}
+
+/// A backup rule from a request: `None` for `""`, which removes the rule.
+fn backup_rule_field(value: &Value) -> Option<Value> {
+    match value {
+        Value::Object(_) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn create_storage(state: &mut State, body: Option<Value>) -> Reply {
+    let spec = match body.as_ref().and_then(|body| body.get("storage")) {
+        Some(spec) => spec.clone(),
+        None => return Reply::error(400, "ACTION_INVALID", "Missing storage."),
+    };
+    let zone = str_field(&spec, "/zone").unwrap_or_default().to_string();
+    if !state.zones.iter().any(|z| z["id"] == zone.as_str()) {
+        return Reply::error(400, "ZONE_INVALID", "The zone is invalid.");
+    }
+    let size = match number_field(&spec, "/size") {
+        Some(size) if (1..=4096).contains(&size) => size,
+        _ => return Reply::error(400, "SIZE_INVALID", "The size is invalid."),
+    };
+    let title = match str_field(&spec, "/title") {
+        Some(title) => String::from(title),
+        None => return Reply::error(400, "TITLE_MISSING", "The title is missing."),
+    };
+    let uuid = state.next_uuid();
+    state.storages.insert(
+        uuid.clone(),
+        Storage {
+            uuid: uuid.clone(),
+            title,
+            size,
+            tier: str_field(&spec, "/tier").unwrap_or("maxiops").to_string(),
+            zone,
+            access: String::from("private"),
+            storage_type: String::from("normal"),
+            state: String::from("online"),
+            license: 0,
+            created: now(),
+            origin: None,
+            servers: vec![],
+            backup_rule: spec.get("backup_rule").and_then(backup_rule_field),
+            backups: vec![],
+            busy_until: None,
+        },
+    );
+    storage_details(state, &uuid, 201)
+}
+
+fn modify_storage(state: &mut State, uuid: &str, body: Option<Value>) -> Reply {
+    let changes = match body.as_ref().and_then(|body| body.get("storage")) {
+        Some(changes) => changes.clone(),
+        None => return Reply::error(400, "ACTION_INVALID", "Missing storage."),
+    };
+    let storage = match state.storages.get_mut(uuid) {
+        Some(storage) => storage,
+        None => return storage_not_found(uuid),
+    };
+    if let Some(size) = number_field(&changes, "/size") {
+        if size < storage.size || size > 4096 {
+            return Reply::error(400, "SIZE_INVALID", "Storages can only be grown.");
+        }
+        storage.size = size;
+    }
+    if let Some(title) = str_field(&changes, "/title") {
+        storage.title = String::from(title);
+    }
+    if let Some(rule) = changes.get("backup_rule") {
+        storage.backup_rule = backup_rule_field(rule);
+    }
+    storage_details(state, uuid, 202)
+}
+
+fn clone_storage(state: &mut State, uuid: &str, body: Option<Value>) -> Reply {
+    let source = match state.storages.get(uuid) {
+        Some(source) => source.clone(),
+        None => return storage_not_found(uuid),
+    };
+    let spec = body
+        .as_ref()
+        .and_then(|body| body.get("storage"))
+        .cloned()
+        .unwrap_or_default();
+    let zone = str_field(&spec, "/zone").unwrap_or_default().to_string();
+    if !state.zones.iter().any(|z| z["id"] == zone.as_str()) {
+        return Reply::error(400, "ZONE_INVALID", "The zone is invalid.");
+    }
+    let title = match str_field(&spec, "/title") {
+        Some(title) => String::from(title),
+        None => return Reply::error(400, "TITLE_MISSING", "The title is missing."),
+    };
+    let clone_uuid = state.next_uuid();
+    state.storages.insert(
+        clone_uuid.clone(),
+        Storage {
+            uuid: clone_uuid.clone(),
+            title,
+            size: source.size,
+            tier: str_field(&spec, "/tier")
+                .map(String::from)
+                .unwrap_or(source.tier),
+            zone,
+            access: String::from("private"),
+            storage_type: String::from("normal"),
+            state: String::from("online"),
+            license: source.license,
+            created: now(),
+            origin: Some(source.uuid),
+            servers: vec![],
+            backup_rule: None,
+            backups: vec![],
+            busy_until: None,
+        },
+    );
+    storage_details(state, &clone_uuid, 201)
+}
+
+fn templatize_storage(state: &mut State, uuid: &str, body: Option<Value>) -> Reply {
+    let source = match state.storages.get(uuid) {
+        Some(source) if source.storage_type == "normal" => source.clone(),
+        Some(_) => {
+            return Reply::error(
+                400,
+                "STORAGE_TYPE_ILLEGAL",
+                "Only normal storages can be templatized.",
+            )
+        }
+        None => return storage_not_found(uuid),
+    };
+    for server in &source.servers {
+        if matches!(state.server(server), Some(s) if s.state != "stopped") {
+            return Reply::error(
+                400,
+                "SERVER_STATE_ILLEGAL",
+                &format!("The storage is attached to the running server {}.", server),
+            );
+        }
+    }
+    let title = match body
+        .as_ref()
+        .and_then(|body| str_field(body, "/storage/title"))
+    {
+        Some(title) => String::from(title),
+        None => return Reply::error(400, "TITLE_MISSING", "The title is missing."),
+    };
+    let delay = state.transition_delay;
+    let template_uuid = state.next_uuid();
+    let mut template = Storage {
+        uuid: template_uuid.clone(),
+        title,
+        access: String::from("private"),
+        storage_type: String::from("template"),
+        created: now(),
+        origin: Some(source.uuid),
+        servers: vec![],
+        backup_rule: None,
+        backups: vec![],
+        ..source
+    };
+    if delay > Duration::from_secs(0) {
+        template.keep_busy(delay);
+        state.storages.get_mut(uuid).unwrap().keep_busy(delay);
+    }
+    state.storages.insert(template_uuid.clone(), template);
+    storage_details(state, &template_uuid, 201)
+}
+
+/// Takes a backup like UpCloud does before growing the filesystem, which
+/// itself only shows as `maintenance` for a while.
+fn resize_storage(state: &mut State, uuid: &str) -> Reply {
+    let source = match state.storages.get(uuid) {
+        Some(source) if source.storage_type == "normal" => source.clone(),
+        Some(_) => {
+            return Reply::error(
+                400,
+                "STORAGE_TYPE_ILLEGAL",
+                "Only normal storages can be resized.",
+            )
+        }
+        None => return storage_not_found(uuid),
+    };
+    for server in &source.servers {
+        if matches!(state.server(server), Some(s) if s.state != "stopped") {
+            return Reply::error(
+                400,
+                "SERVER_STATE_ILLEGAL",
+                &format!("The storage is attached to the running server {}.", server),
+            );
+        }
+    }
+    let delay = state.transition_delay;
+    let backup_uuid = state.next_uuid();
+    let backup = Storage {
+        uuid: backup_uuid.clone(),
+        title: format!("Resize Backup {}", source.title),
+        access: String::from("private"),
+        storage_type: String::from("backup"),
+        created: now(),
+        origin: Some(source.uuid),
+        servers: vec![],
+        backup_rule: None,
+        backups: vec![],
+        busy_until: None,
+        ..source
+    };
+    state.storages.insert(backup_uuid.clone(), backup);
+    let storage = state.storages.get_mut(uuid).unwrap();
+    storage.backups.push(backup_uuid.clone());
+    if delay > Duration::from_secs(0) {
+        storage.keep_busy(delay);
+    }
+    let backup = state.storages[&backup_uuid].to_details_json();
+    Reply::json(200, json!({ "resize_backup": backup }))
+}
+
+fn import_not_found(uuid: &str) -> Reply {
+    Reply::error(
+        404,
+        "STORAGE_IMPORT_NOT_FOUND",
+        &format!("The storage {} has no import.", uuid),
+    )
+}
+
+fn start_import(state: &mut State, uuid: &str, body: Option<Value>) -> Reply {
+    match state.storages.get(uuid) {
+        Some(storage) if storage.storage_type == "normal" => {}
+        Some(_) => {
+            return Reply::error(
+                400,
+                "STORAGE_TYPE_ILLEGAL",
+                "Only normal storages can be imported to.",
+            )
+        }
+        None => return storage_not_found(uuid),
+    }
+    if let Some(import) = state.imports.get(uuid) {
+        if !matches!(
+            import["state"].as_str(),
+            Some("completed" | "failed" | "cancelled")
+        ) {
+            return Reply::error(
+                409,
+                "STORAGE_IMPORT_IN_PROGRESS",
+                "The storage is already being imported to.",
+            );
+        }
+    }
+    let spec = body
+        .as_ref()
+        .and_then(|body| body.get("storage_import"))
+        .cloned()
+        .unwrap_or_default();
+    let import_uuid = state.next_uuid();
+    let mut import = json!({
+        "client_content_length": 0,
+        "client_content_type": "",
+        "completed": "",
+        "created": rfc3339(now()),
+        "direct_upload_url": "",
+        "error_code": "",
+        "error_message": "",
+        "md5sum": "",
+        "read_bytes": 0,
+        "sha256sum": "",
+        "source": spec["source"],
+        "source_type": "",
+        "state": "prepared",
+        "uuid": import_uuid,
+        "written_bytes": 0,
+    });
+    match str_field(&spec, "/source") {
+        Some("direct_upload") => {
+            import["direct_upload_url"] = json!(format!(
+                "{}/uploader/session/{}",
+                state.base_url, import_uuid
+            ));
+        }
+        // The mock can't download anything, so pretend it was quick.
+        Some("http_import") => match str_field(&spec, "/source_location") {
+            Some(location) if location.starts_with("http") => {
+                import["state"] = json!("completed");
+                import["completed"] = json!(rfc3339(now()));
+            }
+            _ => {
+                return Reply::error(
+                    400,
+                    "SOURCE_LOCATION_INVALID",
+                    "The source location is invalid.",
+                )
+            }
+        },
+        _ => return Reply::error(400, "SOURCE_INVALID", "The source is invalid."),
+    }
+    state.imports.insert(String::from(uuid), import.clone());
+    Reply::json(201, json!({ "storage_import": import }))
+}
+
+fn get_import(state: &State, uuid: &str) -> Reply {
+    match state.imports.get(uuid) {
+        Some(import) => Reply::json(200, json!({ "storage_import": import })),
+        None => import_not_found(uuid),
+    }
+}
+
+fn cancel_import(state: &mut State, uuid: &str) -> Reply {
+    let import = match state.imports.get_mut(uuid) {
+        Some(import) => import,
+        None => return import_not_found(uuid),
+    };
+    if matches!(
+        import["state"].as_str(),
+        Some("prepared" | "pending" | "importing")
+    ) {
+        import["state"] = json!("cancelled");
+    }
+    Reply::json(200, json!({ "storage_import": import }))
+}
+
+/// Handles a PUT of image data to an import's `direct_upload_url`.
+/// Decompresses `.gz` and `.xz` uploads the way UpCloud does before
+/// writing them to the disk.
+fn decompress(content_type: &str, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut stored = vec![];
+    match content_type {
+        "application/gzip" => {
+            flate2::read::GzDecoder::new(data).read_to_end(&mut stored)?;
+        }
+        "application/x-xz" => lzma_rs::xz_decompress(&mut &data[..], &mut stored)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?,
+        _ => stored.extend_from_slice(data),
+    }
+    Ok(stored)
+}
+
+pub(crate) fn receive_upload(
+    state: &mut State,
+    session: &str,
+    content_type: &str,
+    data: &[u8],
+) -> Reply {
+    let import = state
+        .imports
+        .values_mut()
+        .find(|import| import["uuid"] == session && import["state"] == "prepared");
+    let import = match import {
+        Some(import) => import,
+        None => return import_not_found(session),
+    };
+    import["client_content_length"] = json!(data.len());
+    import["client_content_type"] = json!(content_type);
+    import["read_bytes"] = json!(data.len());
+    import["completed"] = json!(rfc3339(now()));
+    let stored = match decompress(content_type, data) {
+        Ok(stored) => stored,
+        Err(err) => {
+            import["state"] = json!("failed");
+            import["error_code"] = json!("DECOMPRESSION_FAILED");
+            import["error_message"] = json!(err.to_string());
+            return Reply::error(400, "DECOMPRESSION_FAILED", &err.to_string());
+        }
+    };
+    // UpCloud hashes what it wrote to the disk, not what it received.
+    let sha256sum: String = Sha256::digest(&stored)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    import["written_bytes"] = json!(stored.len());
+    import["sha256sum"] = json!(sha256sum);
+    import["state"] = json!("completed");
+    Reply::json(
+        200,
+        json!({ "written_bytes": stored.len(), "md5sum": "", "sha256sum": sha256sum }),
+    )
+}
+
+fn attach_storage(state: &mut State, server_uuid: &str, body: Option<Value>) -> Reply {
+    let device = body
+        .as_ref()
+        .and_then(|body| body.get("storage_device"))
+        .cloned()
+        .unwrap_or_default();
+    let storage_uuid = str_field(&device, "/storage")
+        .unwrap_or_default()
+        .to_string();
+    let device_type = str_field(&device, "/type").unwrap_or("disk").to_string();
+    let attached_elsewhere = match state.storages.get(&storage_uuid) {
+        Some(storage) => !storage.servers.is_empty(),
+        None => return storage_not_found(&storage_uuid),
+    };
+    if device_type == "disk" && attached_elsewhere {
+        return Reply::error(
+            409,
+            "STORAGE_IN_USE",
+            &format!("The storage {} is attached to a server.", storage_uuid),
+        );
+    }
+    let server = match state.server(server_uuid) {
+        Some(server) => server,
+        None => return server_not_found(server_uuid),
+    };
+    let requested = str_field(&device, "/address").unwrap_or("virtio");
+    let address = if requested.contains(':') {
+        String::from(requested)
+    } else {
+        (0..)
+            .map(|position| format!("{}:{}", requested, position))
+            .find(|address| server.storage_devices.iter().all(|d| &d.address != address))
+            .unwrap_or_else(|| unreachable!())
+    };
+    if server.storage_devices.iter().any(|d| d.address == address) {
+        return Reply::error(
+            409,
+            "DEVICE_ADDRESS_IN_USE",
+            &format!("The address {} is already in use.", address),
+        );
+    }
+    server.storage_devices.push(StorageDevice {
+        address,
+        storage: storage_uuid.clone(),
+        device_type,
+    });
+    if let Some(storage) = state.storages.get_mut(&storage_uuid) {
+        storage.servers.push(String::from(server_uuid));
+    }
+    server_details(state, server_uuid, 202)
+}
+
+/// Only the boot order can be changed so far.
+fn modify_server(state: &mut State, uuid: &str, body: Option<Value>) -> Reply {
+    let server = match state.server(uuid) {
+        Some(server) => server,
+        None => return server_not_found(uuid),
+    };
+    if let Some(boot_order) = body
+        .as_ref()
+        .and_then(|body| str_field(body, "/server/boot_order"))
+    {
+        let valid = boot_order
+            .split(',')
+            .all(|device| matches!(device, "disk" | "cdrom" | "network"));
+        if !valid {
+            return Reply::error(400, "BOOT_ORDER_INVALID", "The boot order is invalid.");
+        }
+        server.boot_order = String::from(boot_order);
+    }
+    if let Some(firewall) = body
+        .as_ref()
+        .and_then(|body| str_field(body, "/server/firewall"))
+    {
+        server.firewall = firewall == "on";
+    }
+    server_details(state, uuid, 202)
+}
+
+/// Every field of a firewall rule but its position. UpCloud shows the ones
+/// a rule doesn't set as empty strings.
+const FIREWALL_RULE_FIELDS: [&str; 14] = [
+    "action",
+    "comment",
+    "destination_address_end",
+    "destination_address_start",
+    "destination_port_end",
+    "destination_port_start",
+    "direction",
+    "family",
+    "icmp_type",
+    "protocol",
+    "source_address_end",
+    "source_address_start",
+    "source_port_end",
+    "source_port_start",
+];
+
+fn firewall_rule_json(rule: &Value, index: usize) -> Value {
+    let mut json = json!({ "position": (index + 1).to_string() });
+    for field in FIREWALL_RULE_FIELDS {
+        json[field] = rule.get(field).cloned().unwrap_or_else(|| json!(""));
+    }
+    json
+}
+
+fn firewall_rule_not_found(position: &str) -> Reply {
+    Reply::error(
+        404,
+        "FIREWALL_RULE_NOT_FOUND",
+        &format!("The firewall rule {} does not exist.", position),
+    )
+}
+
+/// Checks the required fields of a rule and drops its position.
+fn firewall_rule(rule: &Value) -> Result<Value, Reply> {
+    let valid = matches!(str_field(rule, "/direction"), Some("in" | "out"))
+        && matches!(
+            str_field(rule, "/action"),
+            Some("accept" | "reject" | "drop")
+        )
+        && matches!(str_field(rule, "/family"), Some("IPv4" | "IPv6"));
+    if !valid {
+        return Err(Reply::error(
+            400,
+            "FIREWALL_RULE_INVALID",
+            "The firewall rule is invalid.",
+        ));
+    }
+    let mut rule = rule.clone();
+    if let Some(fields) = rule.as_object_mut() {
+        fields.remove("position");
+    }
+    Ok(rule)
+}
+
+fn list_firewall_rules(state: &mut State, server_uuid: &str) -> Reply {
+    match state.server(server_uuid) {
+        Some(server) => {
+            let rules: Vec<Value> = server
+                .firewall_rules
+                .iter()
+                .enumerate()
+                .map(|(index, rule)| firewall_rule_json(rule, index))
+                .collect();
+            Reply::json(200, json!({ "firewall_rules": { "firewall_rule": rules } }))
+        }
+        None => server_not_found(server_uuid),
+    }
+}
+
+/// Index of the rule at the 1-based `position`.
+fn firewall_rule_index(server: &Server, position: &str) -> Result<usize, Reply> {
+    match position.parse::<usize>() {
+        Ok(position) if (1..=server.firewall_rules.len()).contains(&position) => Ok(position - 1),
+        _ => Err(firewall_rule_not_found(position)),
+    }
+}
+
+fn get_firewall_rule(state: &mut State, server_uuid: &str, position: &str) -> Reply {
+    let server = match state.server(server_uuid) {
+        Some(server) => server,
+        None => return server_not_found(server_uuid),
+    };
+    match firewall_rule_index(server, position) {
+        Ok(index) => Reply::json(
+            200,
+            json!({ "firewall_rule": firewall_rule_json(&server.firewall_rules[index], index) }),
+        ),
+        Err(reply) => reply,
+    }
+}
+
+fn create_firewall_rule(state: &mut State, server_uuid: &str, body: Option<Value>) -> Reply {
+    let server = match state.server(server_uuid) {
+        Some(server) => server,
+        None => return server_not_found(server_uuid),
+    };
+    let body = body.unwrap_or_default();
+    let rule = match firewall_rule(&body["firewall_rule"]) {
+        Ok(rule) => rule,
+        Err(reply) => return reply,
+    };
+    let count = server.firewall_rules.len();
+    let index = match number_field(&body, "/firewall_rule/position") {
+        Some(position) if (1..=count as u64 + 1).contains(&position) => position as usize - 1,
+        Some(_) => {
+            return Reply::error(
+                400,
+                "FIREWALL_RULE_INVALID",
+                "The firewall rule position is invalid.",
+            )
+        }
+        None => count,
+    };
+    server.firewall_rules.insert(index, rule);
+    Reply::json(
+        201,
+        json!({ "firewall_rule": firewall_rule_json(&server.firewall_rules[index], index) }),
+    )
+}
+
+fn delete_firewall_rule(state: &mut State, server_uuid: &str, position: &str) -> Reply {
+    let server = match state.server(server_uuid) {
+        Some(server) => server,
+        None => return server_not_found(server_uuid),
+    };
+    match firewall_rule_index(server, position) {
+        Ok(index) => {
+            server.firewall_rules.remove(index);
+            Reply::no_content()
+        }
+        Err(reply) => reply,
+    }
+}
+
+fn replace_firewall_rules(state: &mut State, server_uuid: &str, body: Option<Value>) -> Reply {
+    let server = match state.server(server_uuid) {
+        Some(server) => server,
+        None => return server_not_found(server_uuid),
+    };
+    let body = body.unwrap_or_default();
+    let rules = match body
+        .pointer("/firewall_rules/firewall_rule")
+        .and_then(Value::as_array)
+    {
+        Some(rules) => rules.iter().map(firewall_rule).collect(),
+        None => {
+            return Reply::error(400, "ACTION_INVALID", "Missing firewall_rules.");
+        }
+    };
+    match rules {
+        Ok(rules) => {
+            server.firewall_rules = rules;
+            Reply::no_content()
+        }
+        Err(reply) => reply,
+    }
+}
+
+fn load_cdrom(state: &mut State, server_uuid: &str, body: Option<Value>) -> Reply {
+    let storage_uuid = body
+        .as_ref()
+        .and_then(|body| str_field(body, "/storage_device/storage"))
+        .unwrap_or_default()
+        .to_string();
+    match state.storages.get(&storage_uuid) {
+        Some(storage) if storage.storage_type == "cdrom" => {}
+        Some(_) => {
+            return Reply::error(
+                400,
+                "STORAGE_TYPE_ILLEGAL",
+                "Only CD-ROM storages can be loaded.",
+            )
+        }
+        None => return storage_not_found(&storage_uuid),
+    }
+    let server = match state.server(server_uuid) {
+        Some(server) => server,
+        None => return server_not_found(server_uuid),
+    };
+    let previous = match server
+        .storage_devices
+        .iter_mut()
+        .find(|device| device.device_type == "cdrom")
+    {
+        Some(device) => Some(std::mem::replace(&mut device.storage, storage_uuid.clone())),
+        None => {
+            server.storage_devices.push(StorageDevice {
+                address: String::from("ide:0:0"),
+                storage: storage_uuid.clone(),
+                device_type: String::from("cdrom"),
+            });
+            None
+        }
+    };
+    if let Some(storage) = previous.and_then(|previous| state.storages.get_mut(&previous)) {
+        storage.servers.retain(|server| server != server_uuid);
+    }
+    state
+        .storages
+        .get_mut(&storage_uuid)
+        .unwrap()
+        .servers
+        .push(String::from(server_uuid));
+    server_details(state, server_uuid, 200)
+}
+
+/// The mock has no empty CD-ROM drives, so ejecting removes the drive.
+fn eject_cdrom(state: &mut State, server_uuid: &str) -> Reply {
+    let server = match state.server(server_uuid) {
+        Some(server) => server,
+        None => return server_not_found(server_uuid),
+    };
+    let position = server
+        .storage_devices
+        .iter()
+        .position(|device| device.device_type == "cdrom");
+    let device = match position {
+        Some(position) => server.storage_devices.remove(position),
+        None => {
+            return Reply::error(
+                404,
+                "CDROM_DEVICE_NOT_FOUND",
+                "The server has no CD-ROM device.",
+            )
+        }
+    };
+    if let Some(storage) = state.storages.get_mut(&device.storage) {
+        storage.servers.retain(|server| server != server_uuid);
+    }
+    server_details(state, server_uuid, 200)
+}
+
+fn detach_storage(state: &mut State, server_uuid: &str, body: Option<Value>) -> Reply {
+    let address = body
+        .as_ref()
+        .and_then(|body| str_field(body, "/storage_device/address"))
+        .unwrap_or_default()
+        .to_string();
+    let server = match state.server(server_uuid) {
+        Some(server) => server,
+        None => return server_not_found(server_uuid),
+    };
+    let position = match server
+        .storage_devices
+        .iter()
+        .position(|device| device.address == address)
+    {
+        Some(position) => position,
+        None => {
+            return Reply::error(
+                404,
+                "STORAGE_DEVICE_NOT_FOUND",
+                &format!("No storage is attached at {}.", address),
+            )
+        }
+    };
+    let device = server.storage_devices.remove(position);
+    if let Some(storage) = state.storages.get_mut(&device.storage) {
+        storage.servers.retain(|attached| attached != server_uuid);
+    }
+    server_details(state, server_uuid, 202)
+}
+
+fn ip_address_not_found(address: &str) -> Reply {
+    Reply::error(
+        404,
+        "IP_ADDRESS_NOT_FOUND",
+        &format!("The IP address {} does not exist.", address),
+    )
+}
+
+/// Where `address` is: the server, and the positions of the interface and
+/// of the address in it.
+fn find_ip_address(state: &State, address: &str) -> Option<(String, usize, usize)> {
+    state.servers.values().find_map(|server| {
+        server
+            .interfaces
+            .iter()
+            .enumerate()
+            .find_map(|(interface, addresses)| {
+                let position = addresses
+                    .ip_addresses
+                    .iter()
+                    .position(|ip| ip.address == address)?;
+                Some((server.uuid.clone(), interface, position))
+            })
+    })
+}
+
+/// The server and the position of the public interface with the MAC
+/// address `mac`.
+fn find_public_interface(state: &State, mac: &str) -> Option<(String, usize)> {
+    state.servers.values().find_map(|server| {
+        let position = server
+            .interfaces
+            .iter()
+            .position(|interface| interface.mac == mac && interface.interface_type == "public")?;
+        Some((server.uuid.clone(), position))
+    })
+}
+
+/// Removes the address from wherever it is and tells which zone it's in.
+fn take_ip_address(state: &mut State, address: &str) -> Option<(IpAddress, String)> {
+    if let Some(detached) = state.detached_ips.remove(address) {
+        return Some((detached.ip, detached.zone));
+    }
+    let (server, interface, position) = find_ip_address(state, address)?;
+    let server = state.servers.get_mut(&server).unwrap();
+    let ip = server.interfaces[interface].ip_addresses.remove(position);
+    Some((ip, server.zone.clone()))
+}
+
+fn ip_address_details(state: &State, address: &str, status: u16) -> Reply {
+    if let Some(detached) = state.detached_ips.get(address) {
+        return Reply::json(status, json!({ "ip_address": detached.to_json() }));
+    }
+    match find_ip_address(state, address) {
+        Some((server, interface, position)) => {
+            let server = &state.servers[&server];
+            let interface = &server.interfaces[interface];
+            let ip = interface.ip_addresses[position].to_json(server, interface);
+            Reply::json(status, json!({ "ip_address": ip }))
+        }
+        None => ip_address_not_found(address),
+    }
+}
+
+/// Addresses on private SDN networks aren't listed, like in UpCloud.
+fn list_ip_addresses(state: &State) -> Reply {
+    let mut addresses: Vec<Value> = state
+        .servers
+        .values()
+        .flat_map(|server| {
+            server
+                .interfaces
+                .iter()
+                .filter(|interface| interface.interface_type != "private")
+                .flat_map(move |interface| {
+                    interface
+                        .ip_addresses
+                        .iter()
+                        .map(move |ip| ip.to_json(server, interface))
+                })
+        })
+        .collect();
+    addresses.extend(
+        state
+            .detached_ips
+            .values()
+            .map(|detached| detached.to_json()),
+    );
+    Reply::json(200, json!({ "ip_addresses": { "ip_address": addresses } }))
+}
+
+fn get_ip_address(state: &State, address: &str) -> Reply {
+    ip_address_details(state, address, 200)
+}
+
+/// Adds the address to the server's public or utility interface, creating
+/// the interface if the server has none. Floating addresses go to the
+/// interface with the given MAC address, or nowhere.
+fn assign_ip_address(state: &mut State, body: Option<Value>) -> Reply {
+    let spec = match body.as_ref().and_then(|body| body.get("ip_address")) {
+        Some(spec) => spec.clone(),
+        None => return Reply::error(400, "ACTION_INVALID", "Missing ip_address."),
+    };
+    let interface_type = match str_field(&spec, "/access").unwrap_or("public") {
+        "public" => "public",
+        "private" | "utility" => "utility",
+        _ => return Reply::error(400, "ACCESS_INVALID", "The access is invalid."),
+    };
+    let family = match str_field(&spec, "/family").unwrap_or("IPv4") {
+        family @ ("IPv4" | "IPv6") => String::from(family),
+        _ => return Reply::error(400, "FAMILY_INVALID", "The family is invalid."),
+    };
+    if str_field(&spec, "/floating") == Some("yes") {
+        if interface_type != "public" || family != "IPv4" {
+            return Reply::error(
+                400,
+                "FLOATING_IP_INVALID",
+                "Floating addresses are public IPv4 addresses.",
+            );
+        }
+        return assign_floating_ip_address(state, &spec);
+    }
+    let server_uuid = str_field(&spec, "/server").unwrap_or_default().to_string();
+    let zone = match state.server(&server_uuid) {
+        Some(server) => server.zone.clone(),
+        None => return server_not_found(&server_uuid),
+    };
+    let address = allocate_address(state, interface_type, &family, None);
+    let ip = IpAddress {
+        ptr_record: default_ptr_record(interface_type, &address, &zone),
+        address: address.clone(),
+        family,
+        floating: false,
+    };
+    let index = state.servers[&server_uuid].interfaces.len() as u32 + 1;
+    let mut interface = build_interface(state, &zone, index, &json!({ "type": interface_type }));
+    let server = state.servers.get_mut(&server_uuid).unwrap();
+    match server
+        .interfaces
+        .iter_mut()
+        .find(|existing| existing.interface_type == interface_type)
+    {
+        Some(existing) => existing.ip_addresses.push(ip),
+        None => {
+            interface.ip_addresses.push(ip);
+            server.interfaces.push(interface);
+        }
+    }
+    ip_address_details(state, &address, 201)
+}
+
+fn assign_floating_ip_address(state: &mut State, spec: &Value) -> Reply {
+    let target = match str_field(spec, "/mac") {
+        Some(mac) => match find_public_interface(state, mac) {
+            Some(target) => Some(target),
+            None => return interface_not_found(mac),
+        },
+        None => None,
+    };
+    let zone = match &target {
+        Some((server, _)) => state.servers[server].zone.clone(),
+        None => str_field(spec, "/zone").unwrap_or_default().to_string(),
+    };
+    if !state.zones.iter().any(|z| z["id"] == zone.as_str()) {
+        return Reply::error(400, "ZONE_INVALID", "The zone is invalid.");
+    }
+    let address = allocate_address(state, "public", "IPv4", None);
+    let ip = IpAddress {
+        ptr_record: default_ptr_record("public", &address, &zone),
+        address: address.clone(),
+        family: String::from("IPv4"),
+        floating: true,
+    };
+    match target {
+        Some((server, interface)) => state.servers.get_mut(&server).unwrap().interfaces[interface]
+            .ip_addresses
+            .push(ip),
+        None => {
+            state
+                .detached_ips
+                .insert(address.clone(), DetachedIpAddress { ip, zone });
+        }
+    }
+    ip_address_details(state, &address, 201)
+}
+
+fn interface_not_found(mac: &str) -> Reply {
+    Reply::error(
+        404,
+        "INTERFACE_NOT_FOUND",
+        &format!("No public interface has the MAC address {}.", mac),
+    )
+}
+
+fn modify_ip_address(state: &mut State, address: &str, body: Option<Value>) -> Reply {
+    let changes = body
+        .as_ref()
+        .and_then(|body| body.get("ip_address"))
+        .cloned()
+        .unwrap_or_default();
+    if let Some(ptr_record) = str_field(&changes, "/ptr_record") {
+        let ip = match state.detached_ips.get_mut(address) {
+            Some(detached) => &mut detached.ip,
+            None => match find_ip_address(state, address) {
+                Some((server, interface, position)) => {
+                    let interface =
+                        &mut state.servers.get_mut(&server).unwrap().interfaces[interface];
+                    if interface.interface_type != "public" {
+                        return Reply::error(
+                            400,
+                            "PTR_RECORD_NOT_SUPPORTED",
+                            "Only public addresses have PTR records.",
+                        );
+                    }
+                    &mut interface.ip_addresses[position]
+                }
+                None => return ip_address_not_found(address),
+            },
+        };
+        ip.ptr_record = String::from(ptr_record);
+    }
+    if let Some(mac) = changes.get("mac") {
+        return move_floating_ip_address(state, address, mac.as_str());
+    }
+    ip_address_details(state, address, 202)
+}
+
+/// Moves a floating address to the public interface with the MAC address
+/// `mac`, or detaches it when there's none.
+fn move_floating_ip_address(state: &mut State, address: &str, mac: Option<&str>) -> Reply {
+    let (floating, zone) = match state.detached_ips.get(address) {
+        Some(detached) => (true, detached.zone.clone()),
+        None => match find_ip_address(state, address) {
+            Some((server, interface, position)) => {
+                let server = &state.servers[&server];
+                let ip = &server.interfaces[interface].ip_addresses[position];
+                (ip.floating, server.zone.clone())
+            }
+            None => return ip_address_not_found(address),
+        },
+    };
+    if !floating {
+        return Reply::error(
+            400,
+            "IP_ADDRESS_NOT_FLOATING",
+            &format!("The IP address {} is not floating.", address),
+        );
+    }
+    let target = match mac {
+        Some(mac) => match find_public_interface(state, mac) {
+            Some((server, _)) if state.servers[&server].zone != zone => {
+                return Reply::error(
+                    400,
+                    "ZONE_MISMATCH",
+                    "Floating addresses can only move within their zone.",
+                )
+            }
+            Some(target) => Some(target),
+            None => return interface_not_found(mac),
+        },
+        None => None,
+    };
+    let (ip, zone) = take_ip_address(state, address).unwrap();
+    match target {
+        Some((server, interface)) => state.servers.get_mut(&server).unwrap().interfaces[interface]
+            .ip_addresses
+            .push(ip),
+        None => {
+            state
+                .detached_ips
+                .insert(String::from(address), DetachedIpAddress { ip, zone });
+        }
+    }
+    ip_address_details(state, address, 202)
+}
+
+fn release_ip_address(state: &mut State, address: &str) -> Reply {
+    match take_ip_address(state, address) {
+        Some(_) => Reply::no_content(),
+        None => ip_address_not_found(address),
+    }
+}
+
+fn network_not_found(uuid: &str) -> Reply {
+    Reply::error(
+        404,
+        "NETWORK_NOT_FOUND",
+        &format!("The network {} does not exist.", uuid),
+    )
+}
+
+fn router_not_found(uuid: &str) -> Reply {
+    Reply::error(
+        404,
+        "ROUTER_NOT_FOUND",
+        &format!("The router {} does not exist.", uuid),
+    )
+}
+
+fn network_details(state: &State, uuid: &str, status: u16) -> Reply {
+    match state.networks.get(uuid) {
+        Some(network) => Reply::json(
+            status,
+            json!({ "network": network.to_json(&state.servers) }),
+        ),
+        None => network_not_found(uuid),
+    }
+}
+
+fn list_networks(state: &State, query: &str) -> Reply {
+    let zone = query.split('&').find_map(|pair| pair.strip_prefix("zone="));
+    let networks: Vec<Value> = state
+        .networks
+        .values()
+        .filter(|network| zone.iter().all(|zone| network.zone == *zone))
+        .map(|network| network.to_json(&state.servers))
+        .collect();
+    Reply::json(200, json!({ "networks": { "network": networks } }))
+}
+
+/// The address ranges of a request, if each of them has an address and a family.
+fn ip_networks_field(spec: &Value) -> Option<Vec<Value>> {
+    let ranges = spec.pointer("/ip_networks/ip_network")?.as_array()?;
+    let valid = ranges.iter().all(|range| {
+        str_field(range, "/address").is_some() && str_field(range, "/family").is_some()
+    });
+    if ranges.is_empty() || !valid {
+        return None;
+    }
+    Some(ranges.clone())
+}
+
+fn create_network(state: &mut State, body: Option<Value>) -> Reply {
+    let spec = match body.as_ref().and_then(|body| body.get("network")) {
+        Some(spec) => spec.clone(),
+        None => return Reply::error(400, "ACTION_INVALID", "Missing network."),
+    };
+    let name = match str_field(&spec, "/name") {
+        Some(name) => String::from(name),
+        None => return Reply::error(400, "NAME_MISSING", "The name is missing."),
+    };
+    let zone = str_field(&spec, "/zone").unwrap_or_default().to_string();
+    if !state.zones.iter().any(|z| z["id"] == zone.as_str()) {
+        return Reply::error(400, "ZONE_INVALID", "The zone is invalid.");
+    }
+    let ip_networks = match ip_networks_field(&spec) {
+        Some(ip_networks) => ip_networks,
+        None => return Reply::error(400, "IP_NETWORK_INVALID", "The IP networks are invalid."),
+    };
+    let router = str_field(&spec, "/router").map(String::from);
+    if let Some(router) = &router {
+        if !state.routers.contains_key(router) {
+            return router_not_found(router);
+        }
+    }
+    let uuid = state.next_uuid();
+    state.networks.insert(
+        uuid.clone(),
+        Network {
+            uuid: uuid.clone(),
+            name,
+            zone,
+            ip_networks,
+            router,
+        },
+    );
+    network_details(state, &uuid, 201)
+}
+
+fn modify_network(state: &mut State, uuid: &str, body: Option<Value>) -> Reply {
+    let changes = match body.as_ref().and_then(|body| body.get("network")) {
+        Some(changes) => changes.clone(),
+        None => return Reply::error(400, "ACTION_INVALID", "Missing network."),
+    };
+    let router = match changes.get("router") {
+        Some(Value::String(router)) if !state.routers.contains_key(router) => {
+            return router_not_found(router)
+        }
+        Some(router) => Some(router.as_str().map(String::from)),
+        None => None,
+    };
+    let ip_networks = match changes.get("ip_networks") {
+        Some(_) => match ip_networks_field(&changes) {
+            Some(ip_networks) => Some(ip_networks),
+            None => return Reply::error(400, "IP_NETWORK_INVALID", "The IP networks are invalid."),
+        },
+        None => None,
+    };
+    let network = match state.networks.get_mut(uuid) {
+        Some(network) => network,
+        None => return network_not_found(uuid),
+    };
+    if let Some(name) = str_field(&changes, "/name") {
+        network.name = String::from(name);
+    }
+    if let Some(router) = router {
+        network.router = router;
+    }
+    if let Some(ip_networks) = ip_networks {
+        network.ip_networks = ip_networks;
+    }
+    network_details(state, uuid, 202)
+}
+
+fn delete_network(state: &mut State, uuid: &str) -> Reply {
+    if !state.networks.contains_key(uuid) {
+        return network_not_found(uuid);
+    }
+    let in_use = state.servers.values().any(|server| {
+        server
+            .interfaces
+            .iter()
+            .any(|interface| interface.network == uuid)
+    });
+    if in_use {
+        return Reply::error(
+            409,
+            "NETWORK_IN_USE",
+            &format!("Servers still have interfaces in the network {}.", uuid),
+        );
+    }
+    state.networks.remove(uuid);
+    Reply::no_content()
+}
+
+fn router_details(state: &State, uuid: &str, status: u16) -> Reply {
+    match state.routers.get(uuid) {
+        Some(router) => Reply::json(status, json!({ "router": router.to_json(&state.networks) })),
+        None => router_not_found(uuid),
+    }
+}
+
+fn list_routers(state: &State) -> Reply {
+    let routers: Vec<Value> = state
+        .routers
+        .values()
+        .map(|router| router.to_json(&state.networks))
+        .collect();
+    Reply::json(200, json!({ "routers": { "router": routers } }))
+}
+
+fn create_router(state: &mut State, body: Option<Value>) -> Reply {
+    let name = match body
+        .as_ref()
+        .and_then(|body| str_field(body, "/router/name"))
+    {
+        Some(name) => String::from(name),
+        None => return Reply::error(400, "NAME_MISSING", "The name is missing."),
+    };
+    let uuid = state.next_uuid();
+    state.routers.insert(
+        uuid.clone(),
+        Router {
+            uuid: uuid.clone(),
+            name,
+        },
+    );
+    router_details(state, &uuid, 201)
+}
+
+fn delete_router(state: &mut State, uuid: &str) -> Reply {
+    if !state.routers.contains_key(uuid) {
+        return router_not_found(uuid);
+    }
+    if state
+        .networks
+        .values()
+        .any(|network| network.router.as_deref() == Some(uuid))
+    {
+        return Reply::error(
+            409,
+            "ROUTER_IN_USE",
+            &format!("Networks are still attached to the router {}.", uuid),
+        );
+    }
+    state.routers.remove(uuid);
+    Reply::no_content()
+}
+
+/// The `n`th address of the first IPv4 range of a private network, skipping
+/// the first few for the gateway and DHCP.
+fn private_address(network: &Network, n: u64) -> Option<String> {
+    let range = network
+        .ip_networks
+        .iter()
+        .find(|range| str_field(range, "/family") == Some("IPv4"))?;
+    let (base, _) = str_field(range, "/address")?.split_once('/')?;
+    let base: std::net::Ipv4Addr = base.parse().ok()?;
+    let host = u32::from(base) + 10 + (n % 200) as u32;
+    Some(std::net::Ipv4Addr::from(host).to_string())
+}
+
+/// The server, if it exists and is stopped.
+fn stopped_server<'a>(state: &'a mut State, uuid: &str) -> Result<&'a mut Server, Reply> {
+    match state.server(uuid) {
+        Some(server) if server.state != "stopped" => Err(server_state_illegal(server)),
+        Some(server) => Ok(server),
+        None => Err(server_not_found(uuid)),
+    }
+}
+
+/// Builds an interface out of a request, checking that a private network
+/// exists in the server's zone and picking addresses from its range.
+fn interface_from_spec(
+    state: &mut State,
+    server_uuid: &str,
+    index: u32,
+    spec: &Value,
+) -> Result<Interface, Reply> {
+    let zone = state.servers[server_uuid].zone.clone();
+    let mut spec = spec.clone();
+    if str_field(&spec, "/type") == Some("private") {
+        let network_uuid = str_field(&spec, "/network").unwrap_or_default();
+        let network = match state.networks.get(network_uuid) {
+            Some(network) if network.zone == zone => network.clone(),
+            Some(_) => {
+                return Err(Reply::error(
+                    400,
+                    "ZONE_MISMATCH",
+                    "The network is in another zone than the server.",
+                ))
+            }
+            None => return Err(network_not_found(network_uuid)),
+        };
+        if let Some(addresses) = spec
+            .pointer_mut("/ip_addresses/ip_address")
+            .and_then(Value::as_array_mut)
+        {
+            for ip in addresses {
+                if ip.get("address").is_none() && str_field(ip, "/family") == Some("IPv4") {
+                    let n = state.next_number();
+                    if let Some(address) = private_address(&network, n) {
+                        ip["address"] = json!(address);
+                    }
+                }
+            }
+        }
+    }
+    Ok(build_interface(state, &zone, index, &spec))
+}
+
+fn add_interface(state: &mut State, server_uuid: &str, body: Option<Value>) -> Reply {
+    let spec = match body.as_ref().and_then(|body| body.get("interface")) {
+        Some(spec) => spec.clone(),
+        None => return Reply::error(400, "ACTION_INVALID", "Missing interface."),
+    };
+    let server = match stopped_server(state, server_uuid) {
+        Ok(server) => server,
+        Err(reply) => return reply,
+    };
+    let index = match number_field(&spec, "/index") {
+        Some(index) => index as u32,
+        None => server.interfaces.iter().map(|i| i.index).max().unwrap_or(0) + 1,
+    };
+    if server.interfaces.iter().any(|i| i.index == index) {
+        return Reply::error(
+            409,
+            "INTERFACE_EXISTS",
+            &format!("The server already has an interface at {}.", index),
+        );
+    }
+    let interface = match interface_from_spec(state, server_uuid, index, &spec) {
+        Ok(interface) => interface,
+        Err(reply) => return reply,
+    };
+    let json = interface.to_json();
+    let server = state.servers.get_mut(server_uuid).unwrap();
+    server.interfaces.push(interface);
+    server.interfaces.sort_by_key(|interface| interface.index);
+    Reply::json(201, json!({ "interface": json }))
+}
+
+fn interface_position(server: &Server, index: &str) -> Result<usize, Reply> {
+    server
+        .interfaces
+        .iter()
+        .position(|interface| interface.index.to_string() == index)
+        .ok_or_else(|| {
+            Reply::error(
+                404,
+                "INTERFACE_NOT_FOUND",
+                &format!("The server has no interface at {}.", index),
+            )
+        })
+}
+
+fn list_interfaces(state: &mut State, server_uuid: &str) -> Reply {
+    match state.server(server_uuid) {
+        Some(server) => {
+            let interfaces: Vec<Value> = server.interfaces.iter().map(Interface::to_json).collect();
+            Reply::json(
+                200,
+                json!({ "networking": { "interfaces": { "interface": interfaces } } }),
+            )
+        }
+        None => server_not_found(server_uuid),
+    }
+}
+
+fn get_interface(state: &mut State, server_uuid: &str, index: &str) -> Reply {
+    let server = match state.server(server_uuid) {
+        Some(server) => server,
+        None => return server_not_found(server_uuid),
+    };
+    match interface_position(server, index) {
+        Ok(position) => Reply::json(
+            200,
+            json!({ "interface": server.interfaces[position].to_json() }),
+        ),
+        Err(reply) => reply,
+    }
+}
+
+/// Rebuilds the interface from the request. Addresses the request doesn't
+/// pin down stay as they were when the interface stays in the same network.
+fn modify_interface(
+    state: &mut State,
+    server_uuid: &str,
+    index: &str,
+    body: Option<Value>,
+) -> Reply {
+    let mut spec = match body.as_ref().and_then(|body| body.get("interface")) {
+        Some(spec) => spec.clone(),
+        None => return Reply::error(400, "ACTION_INVALID", "Missing interface."),
+    };
+    let server = match stopped_server(state, server_uuid) {
+        Ok(server) => server,
+        Err(reply) => return reply,
+    };
+    let position = match interface_position(server, index) {
+        Ok(position) => position,
+        Err(reply) => return reply,
+    };
+    let old = server.interfaces[position].clone();
+    let new_index = number_field(&spec, "/index").map_or(old.index, |index| index as u32);
+    if new_index != old.index && server.interfaces.iter().any(|i| i.index == new_index) {
+        return Reply::error(
+            409,
+            "INTERFACE_EXISTS",
+            &format!("The server already has an interface at {}.", new_index),
+        );
+    }
+    if spec.get("type").is_some() || spec.get("network").is_some() {
+        return Reply::error(
+            400,
+            "INTERFACE_TYPE_ILLEGAL",
+            "The type and network of an interface can't be changed.",
+        );
+    }
+    spec["type"] = json!(old.interface_type);
+    spec["network"] = json!(old.network);
+    // Addresses of the same family are kept unless new ones are asked for.
+    let mut kept: Vec<&IpAddress> = old.ip_addresses.iter().collect();
+    if let Some(addresses) = spec
+        .pointer_mut("/ip_addresses/ip_address")
+        .and_then(Value::as_array_mut)
+    {
+        for ip in addresses
+            .iter_mut()
+            .filter(|ip| ip.get("address").is_none())
+        {
+            let family = str_field(ip, "/family").unwrap_or("IPv4").to_string();
+            if let Some(position) = kept.iter().position(|old_ip| old_ip.family == family) {
+                ip["address"] = json!(kept.remove(position).address);
+            }
+        }
+    }
+    let mut interface = match interface_from_spec(state, server_uuid, new_index, &spec) {
+        Ok(interface) => interface,
+        Err(reply) => return reply,
+    };
+    interface.mac = old.mac;
+    interface.network = old.network;
+    let json = interface.to_json();
+    let server = state.servers.get_mut(server_uuid).unwrap();
+    server.interfaces[position] = interface;
+    server.interfaces.sort_by_key(|interface| interface.index);
+    Reply::json(202, json!({ "interface": json }))
+}
+
+fn delete_interface(state: &mut State, server_uuid: &str, index: &str) -> Reply {
+    let server = match stopped_server(state, server_uuid) {
+        Ok(server) => server,
+        Err(reply) => return reply,
+    };
+    match interface_position(server, index) {
+        Ok(position) => {
+            server.interfaces.remove(position);
+            Reply::no_content()
+        }
+        Err(reply) => reply,
+    }
+}
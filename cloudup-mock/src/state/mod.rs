@@ -0,0 +1,650 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{SecondsFormat, TimeZone, Utc};
+use serde_json::{json, Value};
+
+/// A storage known to the mock: templates, disks, backups and CD-ROMs alike.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    pub uuid: String,
+    pub title: String,
+    /// Size in gigabytes.
+    pub size: u64,
+    pub tier: String,
+    pub zone: String,
+    /// `public` or `private`.
+    pub access: String,
+    /// `normal`, `template`, `backup` or `cdrom`.
+    pub storage_type: String,
+    pub state: String,
+    pub license: u64,
+    pub created: u64,
+    /// The storage a backup or a clone was made from.
+    pub origin: Option<String>,
+    /// Servers the storage is attached to.
+    pub servers: Vec<String>,
+    pub backup_rule: Option<Value>,
+    /// Backups taken of the storage.
+    pub backups: Vec<String>,
+    /// The storage shows as `maintenance` until then.
+    pub(crate) busy_until: Option<Instant>,
+}
+
+impl Storage {
+    fn current_state(&self) -> &str {
+        match self.busy_until {
+            Some(until) if Instant::now() < until => "maintenance",
+            _ => &self.state,
+        }
+    }
+
+    /// Puts the storage into `maintenance` for `delay`.
+    pub(crate) fn keep_busy(&mut self, delay: Duration) {
+        self.busy_until = Some(Instant::now() + delay);
+    }
+
+    /// The shape UpCloud lists storages in.
+    pub fn to_list_json(&self) -> Value {
+        let mut storage = json!({
+            "access": self.access,
+            "created": rfc3339(self.created),
+            "labels": { "label": [] },
+            "license": self.license,
+            "size": self.size,
+            "state": self.current_state(),
+            "tier": self.tier,
+            "title": self.title,
+            "type": self.storage_type,
+            "uuid": self.uuid,
+            "zone": self.zone,
+        });
+        if let Some(origin) = &self.origin {
+            storage["origin"] = json!(origin);
+        }
+        storage
+    }
+
+    /// The shape of GET /1.3/storage/{uuid}.
+    pub fn to_details_json(&self) -> Value {
+        let mut storage = json!({
+            "access": self.access,
+            "backup_rule": self.backup_rule.clone().unwrap_or_else(|| json!("")),
+            "backups": { "backup": self.backups },
+            "created": rfc3339(self.created),
+            "license": self.license,
+            "servers": { "server": self.servers },
+            "size": self.size,
+            "state": self.current_state(),
+            "tier": self.tier,
+            "title": self.title,
+            "type": self.storage_type,
+            "uuid": self.uuid,
+            "zone": self.zone,
+        });
+        if let Some(origin) = &self.origin {
+            storage["origin"] = json!(origin);
+        }
+        storage
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IpAddress {
+    pub address: String,
+    /// `IPv4` or `IPv6`.
+    pub family: String,
+    pub floating: bool,
+    /// Reverse DNS name, empty for addresses that aren't public.
+    pub ptr_record: String,
+}
+
+impl IpAddress {
+    /// The shape of GET /1.3/ip_address/{address}.
+    pub fn to_json(&self, server: &Server, interface: &Interface) -> Value {
+        json!({
+            "access": interface.interface_type,
+            "address": self.address,
+            "family": self.family,
+            "floating": yes_no(self.floating),
+            "mac": interface.mac,
+            "part_of_plan": yes_no(interface.index == 1 && self.family == "IPv4"),
+            "ptr_record": self.ptr_record,
+            "server": server.uuid,
+            "zone": server.zone,
+        })
+    }
+}
+
+/// A floating address that isn't attached to any server.
+#[derive(Debug, Clone)]
+pub struct DetachedIpAddress {
+    pub ip: IpAddress,
+    pub zone: String,
+}
+
+impl DetachedIpAddress {
+    /// The shape of GET /1.3/ip_address/{address}.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "access": "public",
+            "address": self.ip.address,
+            "family": self.ip.family,
+            "floating": "yes",
+            "part_of_plan": "no",
+            "ptr_record": self.ip.ptr_record,
+            "zone": self.zone,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub index: u32,
+    /// `public`, `utility` or `private`.
+    pub interface_type: String,
+    pub network: String,
+    pub mac: String,
+    pub ip_addresses: Vec<IpAddress>,
+    pub source_ip_filtering: bool,
+    pub bootable: bool,
+}
+
+impl Interface {
+    /// The shape of an interface in the server details.
+    pub fn to_json(&self) -> Value {
+        let addresses: Vec<Value> = self
+            .ip_addresses
+            .iter()
+            .map(|ip| {
+                json!({
+                    "address": ip.address,
+                    "family": ip.family,
+                    "floating": yes_no(ip.floating),
+                })
+            })
+            .collect();
+        json!({
+            "index": self.index,
+            "ip_addresses": { "ip_address": addresses },
+            "mac": self.mac,
+            "network": self.network,
+            "source_ip_filtering": yes_no(self.source_ip_filtering),
+            "type": self.interface_type,
+            "bootable": yes_no(self.bootable),
+        })
+    }
+}
+
+/// A private SDN network.
+#[derive(Debug, Clone)]
+pub struct Network {
+    pub uuid: String,
+    pub name: String,
+    pub zone: String,
+    /// Address ranges in the shape UpCloud sends them.
+    pub ip_networks: Vec<Value>,
+    pub router: Option<String>,
+}
+
+impl Network {
+    /// The shape of GET /1.3/network/{uuid}.
+    pub fn to_json(&self, servers: &BTreeMap<String, Server>) -> Value {
+        let attached: Vec<Value> = servers
+            .values()
+            .filter(|server| {
+                server
+                    .interfaces
+                    .iter()
+                    .any(|interface| interface.network == self.uuid)
+            })
+            .map(|server| json!({ "title": server.title, "uuid": server.uuid }))
+            .collect();
+        let mut network = json!({
+            "ip_networks": { "ip_network": self.ip_networks },
+            "name": self.name,
+            "servers": { "server": attached },
+            "type": "private",
+            "uuid": self.uuid,
+            "zone": self.zone,
+        });
+        if let Some(router) = &self.router {
+            network["router"] = json!(router);
+        }
+        network
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Router {
+    pub uuid: String,
+    pub name: String,
+}
+
+impl Router {
+    /// The shape of GET /1.3/router/{uuid}.
+    pub fn to_json(&self, networks: &BTreeMap<String, Network>) -> Value {
+        let attached: Vec<Value> = networks
+            .values()
+            .filter(|network| network.router.as_ref() == Some(&self.uuid))
+            .map(|network| json!({ "uuid": network.uuid }))
+            .collect();
+        json!({
+            "attached_networks": { "network": attached },
+            "name": self.name,
+            "type": "normal",
+            "uuid": self.uuid,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageDevice {
+    /// Bus and position, e.g. `virtio:0`.
+    pub address: String,
+    pub storage: String,
+    /// `disk` or `cdrom`.
+    pub device_type: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Server {
+    pub uuid: String,
+    pub title: String,
+    pub hostname: String,
+    pub zone: String,
+    pub plan: String,
+    pub core_number: u32,
+    /// Memory in megabytes.
+    pub memory_amount: u64,
+    pub state: String,
+    /// The state the server ends up in once the running operation is over.
+    pub(crate) pending: Option<(String, Instant)>,
+    pub host: u64,
+    pub created: u64,
+    pub boot_order: String,
+    pub firewall: bool,
+    pub metadata: bool,
+    pub timezone: String,
+    pub simple_backup: String,
+    pub tags: Vec<String>,
+    pub storage_devices: Vec<StorageDevice>,
+    pub interfaces: Vec<Interface>,
+    /// Firewall rules in order, without their positions.
+    pub firewall_rules: Vec<Value>,
+}
+
+impl Server {
+    fn tags_json(&self) -> Value {
+        json!({ "tag": self.tags })
+    }
+
+    /// The shape of a server in GET /1.3/server.
+    pub fn to_list_json(&self) -> Value {
+        json!({
+            "core_number": self.core_number.to_string(),
+            "created": self.created,
+            "host": self.host,
+            "hostname": self.hostname,
+            "license": 0,
+            "memory_amount": self.memory_amount.to_string(),
+            "plan": self.plan,
+            "plan_ipv4_bytes": "0",
+            "plan_ipv6_bytes": "0",
+            "simple_backup": self.simple_backup,
+            "state": self.state,
+            "tags": self.tags_json(),
+            "title": self.title,
+            "uuid": self.uuid,
+            "zone": self.zone,
+        })
+    }
+
+    /// The shape of a server in GET /1.3/server/{uuid}.
+    pub fn to_details_json(&self, storages: &BTreeMap<String, Storage>) -> Value {
+        let ip_addresses: Vec<Value> = self
+            .interfaces
+            .iter()
+            .flat_map(|interface| {
+                interface.ip_addresses.iter().map(move |ip| {
+                    json!({
+                        "access": interface.interface_type,
+                        "address": ip.address,
+                        "family": ip.family,
+                    })
+                })
+            })
+            .collect();
+        let interfaces: Vec<Value> = self.interfaces.iter().map(Interface::to_json).collect();
+        let storage_devices: Vec<Value> = self
+            .storage_devices
+            .iter()
+            .enumerate()
+            .filter_map(|(position, device)| {
+                let storage = storages.get(&device.storage)?;
+                Some(json!({
+                    "address": device.address,
+                    "part_of_plan": if position == 0 { "yes" } else { "no" },
+                    "storage": storage.uuid,
+                    "storage_size": storage.size,
+                    "storage_tier": storage.tier,
+                    "storage_title": storage.title,
+                    "type": device.device_type,
+                    "boot_disk": if position == 0 { "1" } else { "0" },
+                }))
+            })
+            .collect();
+
+        json!({
+            "boot_order": self.boot_order,
+            "core_number": self.core_number.to_string(),
+            "created": self.created,
+            "firewall": if self.firewall { "on" } else { "off" },
+            "host": self.host,
+            "hostname": self.hostname,
+            "ip_addresses": { "ip_address": ip_addresses },
+            "license": 0,
+            "memory_amount": self.memory_amount.to_string(),
+            "metadata": yes_no(self.metadata),
+            "networking": { "interfaces": { "interface": interfaces } },
+            "nic_model": "virtio",
+            "plan": self.plan,
+            "plan_ipv4_bytes": "0",
+            "plan_ipv6_bytes": "0",
+            "remote_access_enabled": "no",
+            "remote_access_password": "aabbccdd",
+            "remote_access_type": "vnc",
+            "simple_backup": self.simple_backup,
+            "state": self.state,
+            "storage_devices": { "storage_device": storage_devices },
+            "tags": self.tags_json(),
+            "timezone": self.timezone,
+            "title": self.title,
+            "uuid": self.uuid,
+            "video_model": "vga",
+            "zone": self.zone,
+        })
+    }
+
+    /// Finishes the running operation if its time has come.
+    pub(crate) fn settle(&mut self) {
+        if let Some((target, ready_at)) = &self.pending {
+            if Instant::now() >= *ready_at {
+                self.state = target.clone();
+                self.pending = None;
+            }
+        }
+    }
+
+    /// Puts the server into `maintenance` until `delay` has passed, then into `target`.
+    pub(crate) fn transition(&mut self, target: &str, delay: Duration) {
+        if delay == Duration::from_secs(0) {
+            self.state = String::from(target);
+            self.pending = None;
+        } else {
+            self.state = String::from("maintenance");
+            self.pending = Some((String::from(target), Instant::now() + delay));
+        }
+    }
+}
+
+pub(crate) fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+pub(crate) fn rfc3339(seconds: u64) -> String {
+    Utc.timestamp_opt(seconds as i64, 0)
+        .unwrap()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Everything the mock API knows. Seeded with a few plans, zones, prices and
+/// public templates; tests are free to change any of it.
+#[derive(Debug)]
+pub struct State {
+    pub account: Value,
+    /// Details of the main account and its sub-accounts by username.
+    pub accounts: BTreeMap<String, Value>,
+    /// Permissions granted to sub-accounts, in the shape GET /1.3/permission lists them.
+    pub permissions: Vec<Value>,
+    pub plans: Vec<Value>,
+    pub zones: Vec<Value>,
+    pub prices: Vec<Value>,
+    pub servers: BTreeMap<String, Server>,
+    pub storages: BTreeMap<String, Storage>,
+    /// How long servers stay in `maintenance` when started, stopped or created.
+    pub transition_delay: Duration,
+    /// Floating addresses that aren't attached to any server, by address.
+    pub detached_ips: BTreeMap<String, DetachedIpAddress>,
+    /// Private networks by UUID. The public and utility networks aren't listed.
+    pub networks: BTreeMap<String, Network>,
+    pub routers: BTreeMap<String, Router>,
+    /// Imports by the UUID of the storage they are into.
+    pub imports: BTreeMap<String, Value>,
+    /// Where the mock listens, for building upload URLs.
+    pub(crate) base_url: String,
+    next_id: u64,
+}
+
+impl State {
+    pub(crate) fn new(username: &str) -> State {
+        let mut state = State {
+            account: json!({
+                "credits": 9972.2324,
+                "username": username,
+                "resource_limits": {
+                    "cores": 200,
+                    "detached_floating_ips": 10,
+                    "memory": 1048576,
+                    "networks": 100,
+                    "public_ipv4": 100,
+                    "public_ipv6": 100,
+                    "storage_hdd": 10240,
+                    "storage_ssd": 10240,
+                },
+            }),
+            accounts: BTreeMap::new(),
+            permissions: vec![],
+            plans: vec![
+                plan("1xCPU-2GB", 1, 2048, 50, 2048),
+                plan("2xCPU-4GB", 2, 4096, 80, 4096),
+                plan("4xCPU-8GB", 4, 8192, 160, 5120),
+            ],
+            zones: vec![
+                zone("de-fra1", "Frankfurt #1"),
+                zone("fi-hel1", "Helsinki #1"),
+                zone("uk-lon1", "London #1"),
+            ],
+            prices: vec![
+                price_zone("de-fra1"),
+                price_zone("fi-hel1"),
+                price_zone("uk-lon1"),
+            ],
+            servers: BTreeMap::new(),
+            storages: BTreeMap::new(),
+            transition_delay: Duration::from_secs(0),
+            detached_ips: BTreeMap::new(),
+            networks: BTreeMap::new(),
+            routers: BTreeMap::new(),
+            imports: BTreeMap::new(),
+            base_url: String::new(),
+            next_id: 0,
+        };
+        state.accounts.insert(
+            String::from(username),
+            account_details(username, username, "main"),
+        );
+        for (title, storage_type) in &[
+            ("CentOS 8", "template"),
+            ("Debian GNU/Linux 10 (Buster)", "template"),
+            ("Ubuntu Server 20.04 LTS", "template"),
+            ("SystemRescue 8.04", "cdrom"),
+        ] {
+            let uuid = state.next_uuid();
+            state.storages.insert(
+                uuid.clone(),
+                Storage {
+                    uuid,
+                    title: String::from(*title),
+                    size: 10,
+                    tier: String::from("maxiops"),
+                    zone: String::from("fi-hel1"),
+                    access: String::from("public"),
+                    storage_type: String::from(*storage_type),
+                    state: String::from("online"),
+                    license: 0,
+                    created: now(),
+                    origin: None,
+                    servers: vec![],
+                    backup_rule: None,
+                    backups: vec![],
+                    busy_until: None,
+                },
+            );
+        }
+        state
+    }
+
+    /// A fresh, UUID shaped identifier.
+    pub fn next_uuid(&mut self) -> String {
+        self.next_id += 1;
+        format!("00000000-0000-4000-8000-{:012x}", self.next_id)
+    }
+
+    /// A fresh number for addresses and the like.
+    pub(crate) fn next_number(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Looks up a server, finishing its running operation first if it's due.
+    pub fn server(&mut self, uuid: &str) -> Option<&mut Server> {
+        let server = self.servers.get_mut(uuid)?;
+        server.settle();
+        Some(server)
+    }
+
+    /// Records a backup of `origin` as if it was taken at `created`, in
+    /// seconds since the epoch.
+    pub fn insert_backup(&mut self, origin: &str, title: &str, created: u64) -> String {
+        let uuid = self.next_uuid();
+        let source = self.storages[origin].clone();
+        self.storages.insert(
+            uuid.clone(),
+            Storage {
+                uuid: uuid.clone(),
+                title: String::from(title),
+                storage_type: String::from("backup"),
+                access: String::from("private"),
+                created,
+                origin: Some(source.uuid),
+                servers: vec![],
+                backup_rule: None,
+                backups: vec![],
+                busy_until: None,
+                ..source
+            },
+        );
+        self.storages
+            .get_mut(origin)
+            .unwrap()
+            .backups
+            .push(uuid.clone());
+        uuid
+    }
+
+    pub fn template(&self, title: &str) -> Option<&Storage> {
+        self.storages
+            .values()
+            .find(|storage| storage.storage_type == "template" && storage.title == title)
+    }
+
+    pub fn cdrom(&self, title: &str) -> Option<&Storage> {
+        self.storages
+            .values()
+            .find(|storage| storage.storage_type == "cdrom" && storage.title == title)
+    }
+}
+
+/// Details of an account as GET /1.3/account/details/{username} shows them.
+pub fn account_details(username: &str, main_account: &str, account_type: &str) -> Value {
+    json!({
+        "main_account": main_account,
+        "type": account_type,
+        "username": username,
+        "first_name": "",
+        "last_name": "",
+        "company": "",
+        "address": "",
+        "postal_code": "",
+        "city": "",
+        "state": "",
+        "country": "FIN",
+        "currency": "EUR",
+        "language": "en",
+        "phone": "",
+        "email": "",
+        "vat_number": "",
+        "timezone": "UTC",
+        "campaigns": { "campaign": [] },
+        "roles": { "role": ["technical"] },
+        "allow_api": "yes",
+        "allow_gui": "yes",
+        "enable_3rd_party_services": "yes",
+        "network_access": { "network": ["*"] },
+        "server_access": { "server": [{ "storage": "yes", "uuid": "*" }] },
+        "storage_access": { "storage": ["*"] },
+        "tag_access": { "tag": [] },
+        "ip_filters": { "ip_filter": [] },
+    })
+}
+
+fn plan(name: &str, cores: u32, memory: u64, storage: u64, traffic: u64) -> Value {
+    json!({
+        "core_number": cores,
+        "memory_amount": memory,
+        "name": name,
+        "public_traffic_out": traffic,
+        "storage_size": storage,
+        "storage_tier": "maxiops",
+    })
+}
+
+fn zone(id: &str, description: &str) -> Value {
+    json!({ "description": description, "id": id, "public": "yes" })
+}
+
+fn price_zone(name: &str) -> Value {
+    let price = |amount: u64, price: f64| json!({ "amount": amount, "price": price });
+    json!({
+        "name": name,
+        "firewall": price(1, 0.56),
+        "io_request_backup": price(1000000, 10.0),
+        "io_request_hdd": price(1000000, 0.0),
+        "io_request_maxiops": price(1000000, 0.0),
+        "ipv4_address": price(1, 0.336),
+        "ipv6_address": price(1, 0.0),
+        "network_private_vlan": price(1, 0.672),
+        "public_ipv4_bandwidth_in": price(1, 0.0),
+        "public_ipv4_bandwidth_out": price(1, 5.6),
+        "public_ipv6_bandwidth_in": price(1, 0.0),
+        "public_ipv6_bandwidth_out": price(1, 5.6),
+        "server_core": price(1, 1.3),
+        "server_memory": price(256, 0.45),
+        "storage_backup": price(1, 0.007),
+        "storage_hdd": price(1, 0.0045),
+        "storage_maxiops": price(1, 0.031),
+        "storage_template": price(1, 0.031),
+    })
+}
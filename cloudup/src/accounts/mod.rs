@@ -1,184 +1,476 @@
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-use crate::Context;
+use crate::request;
+use crate::wire;
+use crate::{Context, Error};
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct ResourceLimits {
     cores: usize,
-    // detached_floating_ips: usize,
+    /// Missing for accounts that predate floating IPs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    detached_floating_ips: Option<usize>,
     memory: usize,
     networks: usize,
     public_ipv4: usize,
     public_ipv6: usize,
     storage_hdd: usize,
     storage_ssd: usize,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Account {
     credits: Decimal,
     username: String,
     resource_limits: ResourceLimits,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct GetAccountResponse {
     // GET /1.3/account
     account: Account,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct AccountRolesField {
-    role: Vec<String>,
+/// What a sub-account is allowed to do.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Manages servers, storages and networks.
+    Technical,
+    /// Sees invoices and pays them.
+    Billing,
+    /// Receives copies of invoices.
+    AuxBilling,
+    /// A role this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct AccountRolesField {
+    pub role: Vec<Role>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct AccountMinimalInfo {
-    roles: AccountRolesField,
+    pub roles: AccountRolesField,
+    /// `main` or `sub`.
     #[serde(rename(serialize = "type", deserialize = "type"))]
-    account_type: String,
-    username: String,
+    pub account_type: String,
+    pub username: String,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct GetAccountListResponseAccountsField {
     account: Vec<AccountMinimalInfo>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct GetAccountListResponse {
     // GET /1.3/account/list
     accounts: GetAccountListResponseAccountsField,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct GetAccountDetailsResponseCampaignsField {
-    campaign: Vec<String>,
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GetAccountDetailsResponseCampaignsField {
+    pub campaign: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct GetAccountDetailsResponseNetworkAccessField {
-    network: Vec<String>,
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GetAccountDetailsResponseNetworkAccessField {
+    pub network: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct ServerAccessPair {
-    storage: String,
-    uuid: String,
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct ServerAccessPair {
+    /// Whether the storages of the server can be accessed as well.
+    #[serde(with = "wire::yes_no")]
+    pub storage: bool,
+    /// The server, or `*` for every server.
+    pub uuid: String,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct GetAccountDetailsResponseServerAccessField {
-    server: Vec<ServerAccessPair>,
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GetAccountDetailsResponseServerAccessField {
+    pub server: Vec<ServerAccessPair>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct GetAccountDetailsResponseStorageAccessField {
-    storage: Vec<String>,
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GetAccountDetailsResponseStorageAccessField {
+    pub storage: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct GetAccountDetailsResponseTagAccessField {
-    tag: Vec<String>,
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GetAccountDetailsResponseTagAccessField {
+    pub tag: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct GetAccountDetailsResponseIpFiltersField {
-    ip_filter: Vec<String>,
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct GetAccountDetailsResponseIpFiltersField {
+    pub ip_filter: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct AccountDetails {
-    main_account: String,
+    pub main_account: String,
+    /// `main` or `sub`.
     #[serde(rename(serialize = "type", deserialize = "type"))]
-    account_type: String,
-    username: String,
-    first_name: String,
-    last_name: String,
-    company: String,
-    address: String,
-    postal_code: String,
-    city: String,
-    state: String,
-    country: String,
-    currency: String,
-    language: String,
-    phone: String,
-    email: String,
-    vat_number: String,
-    timezone: String,
-    campaigns: GetAccountDetailsResponseCampaignsField,
-    roles: AccountRolesField,
-    allow_api: String,
-    allow_gui: String,
-    enable_3rd_party_services: String,
-    network_access: GetAccountDetailsResponseNetworkAccessField,
-    server_access: GetAccountDetailsResponseServerAccessField,
-    storage_access: GetAccountDetailsResponseStorageAccessField,
-    tag_access: GetAccountDetailsResponseTagAccessField,
-    ip_filters: GetAccountDetailsResponseIpFiltersField,
+    pub account_type: String,
+    pub username: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub company: String,
+    pub address: String,
+    pub postal_code: String,
+    pub city: String,
+    pub state: String,
+    pub country: String,
+    pub currency: String,
+    pub language: String,
+    pub phone: String,
+    pub email: String,
+    pub vat_number: String,
+    pub timezone: String,
+    pub campaigns: GetAccountDetailsResponseCampaignsField,
+    pub roles: AccountRolesField,
+    #[serde(with = "wire::yes_no")]
+    pub allow_api: bool,
+    #[serde(with = "wire::yes_no")]
+    pub allow_gui: bool,
+    #[serde(with = "wire::yes_no")]
+    pub enable_3rd_party_services: bool,
+    pub network_access: GetAccountDetailsResponseNetworkAccessField,
+    pub server_access: GetAccountDetailsResponseServerAccessField,
+    pub storage_access: GetAccountDetailsResponseStorageAccessField,
+    pub tag_access: GetAccountDetailsResponseTagAccessField,
+    pub ip_filters: GetAccountDetailsResponseIpFiltersField,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct GetAccountDetailsResponse {
     account: AccountDetails,
 }
 
+/// Contact details, roles and access rights of an account, for
+/// `create_sub_account` and `modify_account`.
+///
+/// Only what's been set is sent, so when modifying an account everything
+/// else stays as it was. Access lists replace the old list as a whole.
+///
+/// ```no_run
+/// # use cloudup::accounts::*;
+/// let settings = AccountSettings::default()
+///     .first_name(String::from("Ci"))
+///     .last_name(String::from("Runner"))
+///     .email(String::from("ci@example.com"))
+///     .role(Role::Technical)
+///     .allow_api(true)
+///     .allow_gui(false)
+///     .ip_filter(String::from("192.0.2.0-192.0.2.255"))
+///     .tag_access(String::from("CI"));
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AccountSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    company: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    postal_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    city: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    country: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vat_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    roles: Option<AccountRolesField>,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    allow_api: Option<bool>,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    allow_gui: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network_access: Option<GetAccountDetailsResponseNetworkAccessField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    server_access: Option<GetAccountDetailsResponseServerAccessField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    storage_access: Option<GetAccountDetailsResponseStorageAccessField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag_access: Option<GetAccountDetailsResponseTagAccessField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ip_filters: Option<GetAccountDetailsResponseIpFiltersField>,
+}
+
+impl AccountSettings {
+    pub fn first_name(mut self, first_name: String) -> AccountSettings {
+        self.first_name = Some(first_name);
+        self
+    }
+
+    pub fn last_name(mut self, last_name: String) -> AccountSettings {
+        self.last_name = Some(last_name);
+        self
+    }
+
+    pub fn company(mut self, company: String) -> AccountSettings {
+        self.company = Some(company);
+        self
+    }
+
+    pub fn address(mut self, address: String) -> AccountSettings {
+        self.address = Some(address);
+        self
+    }
+
+    pub fn postal_code(mut self, postal_code: String) -> AccountSettings {
+        self.postal_code = Some(postal_code);
+        self
+    }
+
+    pub fn city(mut self, city: String) -> AccountSettings {
+        self.city = Some(city);
+        self
+    }
+
+    pub fn state(mut self, state: String) -> AccountSettings {
+        self.state = Some(state);
+        self
+    }
+
+    /// ISO 3166-1 three character country code, e.g. `FIN`.
+    pub fn country(mut self, country: String) -> AccountSettings {
+        self.country = Some(country);
+        self
+    }
+
+    /// e.g. `EUR` or `USD`.
+    pub fn currency(mut self, currency: String) -> AccountSettings {
+        self.currency = Some(currency);
+        self
+    }
+
+    /// e.g. `en` or `fi`.
+    pub fn language(mut self, language: String) -> AccountSettings {
+        self.language = Some(language);
+        self
+    }
+
+    /// In the international format, e.g. `+358.31245434`.
+    pub fn phone(mut self, phone: String) -> AccountSettings {
+        self.phone = Some(phone);
+        self
+    }
+
+    pub fn email(mut self, email: String) -> AccountSettings {
+        self.email = Some(email);
+        self
+    }
+
+    pub fn vat_number(mut self, vat_number: String) -> AccountSettings {
+        self.vat_number = Some(vat_number);
+        self
+    }
+
+    pub fn timezone(mut self, timezone: String) -> AccountSettings {
+        self.timezone = Some(timezone);
+        self
+    }
+
+    pub fn role(mut self, role: Role) -> AccountSettings {
+        self.roles
+            .get_or_insert_with(Default::default)
+            .role
+            .push(role);
+        self
+    }
+
+    /// Whether the account may use the API.
+    pub fn allow_api(mut self, allow_api: bool) -> AccountSettings {
+        self.allow_api = Some(allow_api);
+        self
+    }
+
+    /// Whether the account may log in to the control panel.
+    pub fn allow_gui(mut self, allow_gui: bool) -> AccountSettings {
+        self.allow_gui = Some(allow_gui);
+        self
+    }
+
+    /// Allows logging in from an address or a range such as `192.0.2.0-192.0.2.255`.
+    pub fn ip_filter(mut self, filter: String) -> AccountSettings {
+        self.ip_filters
+            .get_or_insert_with(Default::default)
+            .ip_filter
+            .push(filter);
+        self
+    }
+
+    /// Grants access to a server, or to every server with `*`, and optionally
+    /// to its storages as well.
+    pub fn server_access(mut self, uuid: String, storage: bool) -> AccountSettings {
+        self.server_access
+            .get_or_insert_with(Default::default)
+            .server
+            .push(ServerAccessPair { storage, uuid });
+        self
+    }
+
+    /// Grants access to a storage, or to every storage with `*`.
+    pub fn storage_access(mut self, uuid: String) -> AccountSettings {
+        self.storage_access
+            .get_or_insert_with(Default::default)
+            .storage
+            .push(uuid);
+        self
+    }
+
+    /// Grants access to the servers carrying `tag`.
+    pub fn tag_access(mut self, tag: String) -> AccountSettings {
+        self.tag_access
+            .get_or_insert_with(Default::default)
+            .tag
+            .push(tag);
+        self
+    }
+
+    /// Grants access to a private network, or to every network with `*`.
+    pub fn network_access(mut self, uuid: String) -> AccountSettings {
+        self.network_access
+            .get_or_insert_with(Default::default)
+            .network
+            .push(uuid);
+        self
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct SubAccountFields<'a> {
+    username: &'a str,
+    password: &'a str,
+    #[serde(flatten)]
+    settings: &'a AccountSettings,
+}
+
+#[derive(Serialize, Debug)]
+struct CreateSubAccountPayload<'a> {
+    sub_account: SubAccountFields<'a>,
+}
+
+#[derive(Serialize, Debug)]
+struct ModifyAccountPayload<'a> {
+    account: &'a AccountSettings,
+}
+
 /// GET /1.3/account -> Returns information on the user's account and resource limits.
-pub async fn get_account_info(ctx: &Context) -> Result<Account, reqwest::Error> {
-    let url = format!("{}/1.3/account", ctx.uc_baseurl);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .get(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let response: Result<GetAccountResponse, _> = match request {
-        Ok(response) => response.json::<GetAccountResponse>().await,
-        Err(error) => Err(error),
-    };
-    return match response {
-        Ok(payload) => Ok(payload.account),
-        Err(err) => Err(err),
-    };
+pub async fn get_account_info(ctx: &Context) -> Result<Account, Error> {
+    let response: GetAccountResponse = request::get(ctx, "/account").await?;
+    Ok(response.account)
 }
 
 /// GET /1.3/account/list
-pub async fn get_account_list(ctx: &Context) -> Result<Vec<AccountMinimalInfo>, reqwest::Error> {
-    let url = format!("{}/1.3/account/list", ctx.uc_baseurl);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .get(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let response: Result<GetAccountListResponse, _> = match request {
-        Ok(response) => response.json::<GetAccountListResponse>().await,
-        Err(error) => {
-            panic!("{}", error);
-        }
-    };
-    return match response {
-        Ok(payload) => Ok(payload.accounts.account),
-        Err(err) => Err(err),
+pub async fn get_account_list(ctx: &Context) -> Result<Vec<AccountMinimalInfo>, Error> {
+    let response: GetAccountListResponse = request::get(ctx, "/account/list").await?;
+    Ok(response.accounts.account)
+}
+
+/// GET /1.3/account/details/{username}
+pub async fn get_account_details(ctx: &Context, username: String) -> Result<AccountDetails, Error> {
+    let response: GetAccountDetailsResponse =
+        request::get(ctx, &format!("/account/details/{}", username)).await?;
+    Ok(response.account)
+}
+
+/// POST /1.3/account/sub -> Creates a sub-account under the main account.
+pub async fn create_sub_account(
+    ctx: &Context,
+    username: String,
+    password: String,
+    settings: &AccountSettings,
+) -> Result<AccountDetails, Error> {
+    let payload = CreateSubAccountPayload {
+        sub_account: SubAccountFields {
+            username: &username,
+            password: &password,
+            settings,
+        },
     };
+    let response: GetAccountDetailsResponse = request::post(ctx, "/account/sub", &payload).await?;
+    Ok(response.account)
+}
+
+/// PUT /1.3/account/details/{username} -> Changes what's set in `settings`.
+pub async fn modify_account(
+    ctx: &Context,
+    username: String,
+    settings: &AccountSettings,
+) -> Result<AccountDetails, Error> {
+    let payload = ModifyAccountPayload { account: settings };
+    let response: GetAccountDetailsResponse =
+        request::put(ctx, &format!("/account/details/{}", username), &payload).await?;
+    Ok(response.account)
+}
+
+/// DELETE /1.3/account/sub/{username}
+pub async fn delete_sub_account(ctx: &Context, username: String) -> Result<(), Error> {
+    request::delete(ctx, &format!("/account/sub/{}", username)).await
 }
 
 #[cfg(test)]
@@ -191,11 +483,15 @@ mod tests {
     fn get_account() {
         let account = GetAccountResponse {
             account: Account {
+                #[cfg(feature = "lenient")]
+                extra: Default::default(),
                 credits: dec!(9972.2324),
                 username: String::from("username"),
                 resource_limits: ResourceLimits {
+                    #[cfg(feature = "lenient")]
+                    extra: Default::default(),
                     cores: 200,
-                    // detached_floating_ips: 10,
+                    detached_floating_ips: Some(10),
                     memory: 1048576,
                     networks: 100,
                     public_ipv4: 100,
@@ -215,22 +511,28 @@ mod tests {
             accounts: GetAccountListResponseAccountsField {
                 account: vec![
                     AccountMinimalInfo {
+                        #[cfg(feature = "lenient")]
+                        extra: Default::default(),
                         roles: AccountRolesField {
-                            role: vec![String::from("technical")],
+                            role: vec![Role::Technical],
                         },
                         account_type: String::from("mymain"),
                         username: String::from("test"),
                     },
                     AccountMinimalInfo {
+                        #[cfg(feature = "lenient")]
+                        extra: Default::default(),
                         roles: AccountRolesField {
-                            role: vec![String::from("technical")],
+                            role: vec![Role::Technical],
                         },
                         account_type: String::from("sub"),
                         username: String::from("my_sub_account"),
                     },
                     AccountMinimalInfo {
+                        #[cfg(feature = "lenient")]
+                        extra: Default::default(),
                         roles: AccountRolesField {
-                            role: vec![String::from("billing")],
+                            role: vec![Role::Billing],
                         },
                         account_type: String::from("sub"),
                         username: String::from("my_billing_account"),
@@ -246,6 +548,8 @@ mod tests {
     fn get_account_details() {
         let account_details = GetAccountDetailsResponse {
             account: AccountDetails {
+                #[cfg(feature = "lenient")]
+                extra: Default::default(),
                 main_account: String::from("mymain"),
                 account_type: String::from("sub"),
                 username: String::from("my_sub_account"),
@@ -265,13 +569,13 @@ mod tests {
                 timezone: String::from("UTC"),
                 campaigns: GetAccountDetailsResponseCampaignsField { campaign: vec![] },
                 roles: AccountRolesField { role: vec![] },
-                allow_api: String::from("yes"),
-                allow_gui: String::from("no"),
-                enable_3rd_party_services: String::from("yes"),
+                allow_api: true,
+                allow_gui: false,
+                enable_3rd_party_services: true,
                 network_access: GetAccountDetailsResponseNetworkAccessField { network: vec![] },
                 server_access: GetAccountDetailsResponseServerAccessField {
                     server: vec![ServerAccessPair {
-                        storage: String::from("no"),
+                        storage: false,
                         uuid: String::from("*"),
                     }],
                 },
@@ -285,4 +589,38 @@ mod tests {
 
         println!("{}", serde_json::to_string(&account_details).unwrap());
     }
+
+    #[test]
+    fn sub_account_wire_format() {
+        let settings = AccountSettings::default()
+            .email(String::from("ci@example.com"))
+            .role(Role::Technical)
+            .allow_api(true)
+            .allow_gui(false)
+            .server_access(String::from("*"), false)
+            .tag_access(String::from("CI"));
+        let payload = CreateSubAccountPayload {
+            sub_account: SubAccountFields {
+                username: "ci",
+                password: "hunter22",
+                settings: &settings,
+            },
+        };
+
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::json!({
+                "sub_account": {
+                    "username": "ci",
+                    "password": "hunter22",
+                    "email": "ci@example.com",
+                    "roles": { "role": ["technical"] },
+                    "allow_api": "yes",
+                    "allow_gui": "no",
+                    "server_access": { "server": [{ "storage": "no", "uuid": "*" }] },
+                    "tag_access": { "tag": ["CI"] }
+                }
+            })
+        );
+    }
 }
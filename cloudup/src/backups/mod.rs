@@ -0,0 +1,248 @@
+//! Pruning of backup storages by a grandfather-father-son retention policy.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Datelike, Utc};
+
+use crate::storage::{self, Storage, StorageFilter};
+use crate::{Context, Error};
+
+/// How many backups `prune` keeps of each disk.
+///
+/// Of each of the `daily` most recent days with backups the newest backup is
+/// kept, and likewise for weeks and months. A backup kept for one reason
+/// counts for the others too, so `RetentionPolicy::new(7, 4, 12)` keeps at
+/// most 23 backups of a disk.
+///
+/// ```no_run
+/// # use cloudup::backups::RetentionPolicy;
+/// let policy = RetentionPolicy::new(7, 4, 12).dry_run(true);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    pub daily: usize,
+    pub weekly: usize,
+    pub monthly: usize,
+    /// Only report what would be deleted.
+    pub dry_run: bool,
+}
+
+impl RetentionPolicy {
+    pub fn new(daily: usize, weekly: usize, monthly: usize) -> RetentionPolicy {
+        RetentionPolicy {
+            daily,
+            weekly,
+            monthly,
+            dry_run: false,
+        }
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> RetentionPolicy {
+        self.dry_run = dry_run;
+        self
+    }
+}
+
+/// What `prune` kept and deleted, or would have deleted on a dry run.
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub kept: Vec<Storage>,
+    pub deleted: Vec<Storage>,
+    /// Backups the policy doesn't keep that weren't deleted because a delete
+    /// failed, starting with the one that failed.
+    pub left: Vec<Storage>,
+    /// Why the backups in `left` weren't deleted.
+    pub error: Option<Error>,
+    pub dry_run: bool,
+}
+
+/// Deletes the backups that `policy` doesn't keep.
+///
+/// Backups are grouped by the disk they were taken of, and each disk is
+/// judged on its own. Backups UpCloud doesn't tell the creation time or the
+/// origin of are always kept, as there's nothing to judge them against.
+///
+/// Stops at the first delete that fails. The report then tells which
+/// backups were deleted before that, in `deleted`, and which weren't, in
+/// `left`, along with the `error`.
+pub async fn prune(ctx: &Context, policy: &RetentionPolicy) -> Result<PruneReport, Error> {
+    let backups = storage::list(ctx, StorageFilter::Backup).await?;
+    let mut report = judge(backups, policy);
+    if !policy.dry_run {
+        let mut doomed = std::mem::take(&mut report.deleted).into_iter();
+        for backup in doomed.by_ref() {
+            if let Err(err) = storage::delete(ctx, backup.uuid.clone()).await {
+                report.left.push(backup);
+                report.error = Some(err);
+                break;
+            }
+            report.deleted.push(backup);
+        }
+        report.left.extend(doomed);
+    }
+    Ok(report)
+}
+
+/// Splits `backups` into the ones `policy` keeps and the ones it doesn't.
+fn judge(backups: Vec<Storage>, policy: &RetentionPolicy) -> PruneReport {
+    let mut report = PruneReport {
+        dry_run: policy.dry_run,
+        ..PruneReport::default()
+    };
+    let mut by_origin: BTreeMap<String, Vec<Storage>> = BTreeMap::new();
+    for backup in backups {
+        match backup.origin.clone() {
+            Some(origin) => by_origin.entry(origin).or_default().push(backup),
+            None => report.kept.push(backup),
+        }
+    }
+
+    for backups in by_origin.into_values() {
+        let keep = retained(&backups, policy);
+        for backup in backups {
+            if keep.contains(&backup.uuid) {
+                report.kept.push(backup);
+            } else {
+                report.deleted.push(backup);
+            }
+        }
+    }
+    report
+}
+
+/// UUIDs of the backups of a single disk that `policy` keeps.
+fn retained(backups: &[Storage], policy: &RetentionPolicy) -> BTreeSet<String> {
+    let mut keep: BTreeSet<String> = backups
+        .iter()
+        .filter(|backup| backup.created.is_none())
+        .map(|backup| backup.uuid.clone())
+        .collect();
+
+    let mut dated: Vec<(DateTime<Utc>, &str)> = backups
+        .iter()
+        .filter_map(|backup| Some((backup.created?, backup.uuid.as_str())))
+        .collect();
+    dated.sort_unstable_by(|a, b| b.cmp(a));
+
+    keep_newest_per(
+        &dated,
+        policy.daily,
+        |created| created.date_naive(),
+        &mut keep,
+    );
+    keep_newest_per(
+        &dated,
+        policy.weekly,
+        |created| created.iso_week(),
+        &mut keep,
+    );
+    keep_newest_per(
+        &dated,
+        policy.monthly,
+        |created| (created.year(), created.month()),
+        &mut keep,
+    );
+    keep
+}
+
+/// Keeps the newest backup in each of the `count` most recent periods.
+/// `dated` goes from the newest backup to the oldest.
+fn keep_newest_per<P: PartialEq>(
+    dated: &[(DateTime<Utc>, &str)],
+    count: usize,
+    period: impl Fn(&DateTime<Utc>) -> P,
+    keep: &mut BTreeSet<String>,
+) {
+    let mut last = None;
+    let mut kept = 0;
+    for (created, uuid) in dated {
+        if kept == count {
+            break;
+        }
+        let current = period(created);
+        if last.as_ref() != Some(&current) {
+            keep.insert(String::from(*uuid));
+            last = Some(current);
+            kept += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    fn backup(uuid: &str, created: &str) -> Storage {
+        serde_json::from_value(json!({
+            "access": "private",
+            "created": created,
+            "license": 0,
+            "origin": "disk",
+            "size": 10,
+            "state": "online",
+            "title": uuid,
+            "type": "backup",
+            "uuid": uuid
+        }))
+        .unwrap()
+    }
+
+    fn uuids(uuids: &[&str]) -> BTreeSet<String> {
+        uuids.iter().map(|uuid| uuid.to_string()).collect()
+    }
+
+    #[test]
+    fn keeps_newest_of_each_period() {
+        let backups = vec![
+            backup("mon-late", "2021-06-14T20:00:00Z"),
+            backup("mon-early", "2021-06-14T04:00:00Z"),
+            backup("sun", "2021-06-13T04:00:00Z"),
+            backup("sat", "2021-06-12T04:00:00Z"),
+            backup("prev-week", "2021-06-02T04:00:00Z"),
+            backup("may", "2021-05-20T04:00:00Z"),
+            backup("april", "2021-04-20T04:00:00Z"),
+        ];
+
+        let days = retained(&backups, &RetentionPolicy::new(2, 0, 0));
+        assert_eq!(days, uuids(&["mon-late", "sun"]));
+
+        // The week of Monday 14th, the one of Sunday 13th and the one before.
+        let weeks = retained(&backups, &RetentionPolicy::new(0, 3, 0));
+        assert_eq!(weeks, uuids(&["mon-late", "sun", "prev-week"]));
+
+        let all = retained(&backups, &RetentionPolicy::new(1, 2, 2));
+        assert_eq!(all, uuids(&["mon-late", "sun", "may"]));
+    }
+
+    #[test]
+    fn keeps_undated_backups() {
+        let mut undated = backup("undated", "2021-06-14T20:00:00Z");
+        undated.created = None;
+
+        let keep = retained(&[undated], &RetentionPolicy::new(0, 0, 0));
+
+        assert!(keep.contains("undated"));
+    }
+
+    #[test]
+    fn keeps_backups_without_an_origin() {
+        let mut first = backup("first", "2021-06-14T20:00:00Z");
+        first.origin = None;
+        let mut second = backup("second", "2021-06-14T04:00:00Z");
+        second.origin = None;
+        let older = backup("older", "2021-06-13T04:00:00Z");
+        let newer = backup("newer", "2021-06-14T04:00:00Z");
+
+        let report = judge(
+            vec![first, second, older, newer],
+            &RetentionPolicy::new(1, 0, 0),
+        );
+
+        let kept: Vec<&str> = report.kept.iter().map(|b| b.uuid.as_str()).collect();
+        let deleted: Vec<&str> = report.deleted.iter().map(|b| b.uuid.as_str()).collect();
+        assert_eq!(kept, ["first", "second", "newer"]);
+        assert_eq!(deleted, ["older"]);
+    }
+}
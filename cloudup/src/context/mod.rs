@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use crate::retry::RetryPolicy;
+use crate::Error;
+
+pub const DEFAULT_BASEURL: &str = "https://api.upcloud.com";
+
+pub(crate) struct Credentials {
+    pub(crate) user: String,
+    pub(crate) pass: Option<String>,
+}
+
+/// Everything needed to talk to the UpCloud API: where it is, who we are and
+/// the HTTP client every request goes through.
+///
+/// The client keeps a connection pool, so share one `Context` between calls
+/// instead of building a new one per request.
+pub struct Context {
+    pub(crate) uc_baseurl: String,
+    pub(crate) credentials: Credentials,
+    pub(crate) client: reqwest::Client,
+    pub(crate) retry_policy: RetryPolicy,
+}
+
+impl Context {
+    /// A context with a default HTTP client. Use `Context::builder` to tune it.
+    pub fn new(endpoint: String, user: String, password: String) -> Context {
+        Context {
+            uc_baseurl: String::from(endpoint.trim_end_matches('/')),
+            credentials: Credentials {
+                user,
+                pass: Some(password),
+            },
+            client: reqwest::Client::new(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    pub fn builder(user: String, password: String) -> ContextBuilder {
+        ContextBuilder::new(user, password)
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.uc_baseurl
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+}
+
+pub struct ContextBuilder {
+    uc_baseurl: String,
+    credentials: Credentials,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: String,
+    proxies: Vec<reqwest::Proxy>,
+    root_certificates: Vec<reqwest::Certificate>,
+    retry_policy: RetryPolicy,
+}
+
+impl ContextBuilder {
+    pub fn new(user: String, password: String) -> ContextBuilder {
+        ContextBuilder {
+            uc_baseurl: String::from(DEFAULT_BASEURL),
+            credentials: Credentials {
+                user,
+                pass: Some(password),
+            },
+            timeout: None,
+            connect_timeout: None,
+            user_agent: format!("cloudup/{}", env!("CARGO_PKG_VERSION")),
+            proxies: vec![],
+            root_certificates: vec![],
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Defaults to `DEFAULT_BASEURL`.
+    pub fn base_url(mut self, base_url: String) -> ContextBuilder {
+        self.uc_baseurl = String::from(base_url.trim_end_matches('/'));
+        self
+    }
+
+    /// Total time allowed for a single request, from connecting to reading the body.
+    pub fn timeout(mut self, timeout: Duration) -> ContextBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> ContextBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: String) -> ContextBuilder {
+        self.user_agent = user_agent;
+        self
+    }
+
+    /// Routes requests through the given proxy. Can be called more than once.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> ContextBuilder {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Trusts an extra root certificate on top of the built-in ones.
+    pub fn add_root_certificate(mut self, certificate: reqwest::Certificate) -> ContextBuilder {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Defaults to `RetryPolicy::default()`. Pass `RetryPolicy::none()` to disable retries.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> ContextBuilder {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn build(self) -> Result<Context, Error> {
+        let mut client = reqwest::Client::builder().user_agent(self.user_agent);
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        for proxy in self.proxies {
+            client = client.proxy(proxy);
+        }
+        for certificate in self.root_certificates {
+            client = client.add_root_certificate(certificate);
+        }
+
+        Ok(Context {
+            uc_baseurl: self.uc_baseurl,
+            credentials: self.credentials,
+            client: client.build()?,
+            retry_policy: self.retry_policy,
+        })
+    }
+}
@@ -0,0 +1,344 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Error codes UpCloud reports in the `error_code` field of a failed request.
+///
+/// Codes the library doesn't know about yet are kept verbatim in `Other`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorCode {
+    AccountNotFound,
+    ActionInvalid,
+    ActionMissing,
+    AuthenticationFailed,
+    BackupNotFound,
+    CoresInvalid,
+    FirewallRuleNotFound,
+    HostnameInvalid,
+    InsufficientCredits,
+    InternalError,
+    IpAddressForbidden,
+    IpAddressNotFound,
+    IpAddressResourcesUnavailable,
+    MemoryInvalid,
+    NetworkNotFound,
+    PlanNotFound,
+    ResourceForbidden,
+    RouterNotFound,
+    ServerForbidden,
+    ServerNotFound,
+    ServerStateIllegal,
+    SizeInvalid,
+    StorageForbidden,
+    StorageInUse,
+    StorageNotFound,
+    StorageStateIllegal,
+    TagNotFound,
+    TierInvalid,
+    TitleInvalid,
+    TitleMissing,
+    TooManyRequests,
+    ZoneNotFound,
+    Other(String),
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ErrorCode::AccountNotFound => "ACCOUNT_NOT_FOUND",
+            ErrorCode::ActionInvalid => "ACTION_INVALID",
+            ErrorCode::ActionMissing => "ACTION_MISSING",
+            ErrorCode::AuthenticationFailed => "AUTHENTICATION_FAILED",
+            ErrorCode::BackupNotFound => "BACKUP_NOT_FOUND",
+            ErrorCode::CoresInvalid => "CORES_INVALID",
+            ErrorCode::FirewallRuleNotFound => "FIREWALL_RULE_NOT_FOUND",
+            ErrorCode::HostnameInvalid => "HOSTNAME_INVALID",
+            ErrorCode::InsufficientCredits => "INSUFFICIENT_CREDITS",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+            ErrorCode::IpAddressForbidden => "IP_ADDRESS_FORBIDDEN",
+            ErrorCode::IpAddressNotFound => "IP_ADDRESS_NOT_FOUND",
+            ErrorCode::IpAddressResourcesUnavailable => "IP_ADDRESS_RESOURCES_UNAVAILABLE",
+            ErrorCode::MemoryInvalid => "MEMORY_INVALID",
+            ErrorCode::NetworkNotFound => "NETWORK_NOT_FOUND",
+            ErrorCode::PlanNotFound => "PLAN_NOT_FOUND",
+            ErrorCode::ResourceForbidden => "RESOURCE_FORBIDDEN",
+            ErrorCode::RouterNotFound => "ROUTER_NOT_FOUND",
+            ErrorCode::ServerForbidden => "SERVER_FORBIDDEN",
+            ErrorCode::ServerNotFound => "SERVER_NOT_FOUND",
+            ErrorCode::ServerStateIllegal => "SERVER_STATE_ILLEGAL",
+            ErrorCode::SizeInvalid => "SIZE_INVALID",
+            ErrorCode::StorageForbidden => "STORAGE_FORBIDDEN",
+            ErrorCode::StorageInUse => "STORAGE_IN_USE",
+            ErrorCode::StorageNotFound => "STORAGE_NOT_FOUND",
+            ErrorCode::StorageStateIllegal => "STORAGE_STATE_ILLEGAL",
+            ErrorCode::TagNotFound => "TAG_NOT_FOUND",
+            ErrorCode::TierInvalid => "TIER_INVALID",
+            ErrorCode::TitleInvalid => "TITLE_INVALID",
+            ErrorCode::TitleMissing => "TITLE_MISSING",
+            ErrorCode::TooManyRequests => "TOO_MANY_REQUESTS",
+            ErrorCode::ZoneNotFound => "ZONE_NOT_FOUND",
+            ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl From<&str> for ErrorCode {
+    fn from(code: &str) -> ErrorCode {
+        match code {
+            "ACCOUNT_NOT_FOUND" => ErrorCode::AccountNotFound,
+            "ACTION_INVALID" => ErrorCode::ActionInvalid,
+            "ACTION_MISSING" => ErrorCode::ActionMissing,
+            "AUTHENTICATION_FAILED" => ErrorCode::AuthenticationFailed,
+            "BACKUP_NOT_FOUND" => ErrorCode::BackupNotFound,
+            "CORES_INVALID" => ErrorCode::CoresInvalid,
+            "FIREWALL_RULE_NOT_FOUND" => ErrorCode::FirewallRuleNotFound,
+            "HOSTNAME_INVALID" => ErrorCode::HostnameInvalid,
+            "INSUFFICIENT_CREDITS" => ErrorCode::InsufficientCredits,
+            "INTERNAL_ERROR" => ErrorCode::InternalError,
+            "IP_ADDRESS_FORBIDDEN" => ErrorCode::IpAddressForbidden,
+            "IP_ADDRESS_NOT_FOUND" => ErrorCode::IpAddressNotFound,
+            "IP_ADDRESS_RESOURCES_UNAVAILABLE" => ErrorCode::IpAddressResourcesUnavailable,
+            "MEMORY_INVALID" => ErrorCode::MemoryInvalid,
+            "NETWORK_NOT_FOUND" => ErrorCode::NetworkNotFound,
+            "PLAN_NOT_FOUND" => ErrorCode::PlanNotFound,
+            "RESOURCE_FORBIDDEN" => ErrorCode::ResourceForbidden,
+            "ROUTER_NOT_FOUND" => ErrorCode::RouterNotFound,
+            "SERVER_FORBIDDEN" => ErrorCode::ServerForbidden,
+            "SERVER_NOT_FOUND" => ErrorCode::ServerNotFound,
+            "SERVER_STATE_ILLEGAL" => ErrorCode::ServerStateIllegal,
+            "SIZE_INVALID" => ErrorCode::SizeInvalid,
+            "STORAGE_FORBIDDEN" => ErrorCode::StorageForbidden,
+            "STORAGE_IN_USE" => ErrorCode::StorageInUse,
+            "STORAGE_NOT_FOUND" => ErrorCode::StorageNotFound,
+            "STORAGE_STATE_ILLEGAL" => ErrorCode::StorageStateIllegal,
+            "TAG_NOT_FOUND" => ErrorCode::TagNotFound,
+            "TIER_INVALID" => ErrorCode::TierInvalid,
+            "TITLE_INVALID" => ErrorCode::TitleInvalid,
+            "TITLE_MISSING" => ErrorCode::TitleMissing,
+            "TOO_MANY_REQUESTS" => ErrorCode::TooManyRequests,
+            "ZONE_NOT_FOUND" => ErrorCode::ZoneNotFound,
+            other => ErrorCode::Other(String::from(other)),
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error reported by the UpCloud API itself.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: reqwest::StatusCode,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.status, self.code, self.message)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponseErrorField {
+    error_code: String,
+    error_message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: ErrorResponseErrorField,
+}
+
+/// Everything that can go wrong when talking to UpCloud.
+///
+/// New variants may be added in minor releases, so matches on it need a
+/// wildcard arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The request could not be sent or its response could not be read.
+    Transport(reqwest::Error),
+    /// The response body didn't match the type it was supposed to be.
+    Decode {
+        source: serde_json::Error,
+        body: String,
+    },
+    /// UpCloud refused the request and told us why.
+    Api(ApiError),
+    /// UpCloud answered with an unexpected status and no error payload.
+    UnexpectedStatus {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    /// Waiting for a resource to reach some state took longer than allowed.
+    Timeout(Duration),
+    /// Waiting for a resource was cancelled by the caller.
+    Cancelled,
+    /// A local file could not be read.
+    Io(std::io::Error),
+    /// UpCloud gave up importing a storage.
+    ImportFailed { code: String, message: String },
+    /// What UpCloud received doesn't hash to what was sent.
+    ChecksumMismatch { expected: String, actual: String },
+    /// The server with this UUID has no disk attached.
+    NoDisk(String),
+    /// The server with this UUID has no public network interface.
+    NoPublicInterface(String),
+    /// A floating IP address isn't attached to the server it should be on.
+    UnexpectedAttachment {
+        address: String,
+        expected: String,
+        actual: Option<String>,
+    },
+}
+
+impl Error {
+    /// Builds an error out of a non-successful response body, preferring
+    /// UpCloud's own error payload when there is one.
+    pub(crate) fn from_response(status: reqwest::StatusCode, body: String) -> Error {
+        match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(payload) => Error::Api(ApiError {
+                status,
+                code: ErrorCode::from(payload.error.error_code.as_str()),
+                message: payload.error.error_message,
+            }),
+            Err(_) => Error::UnexpectedStatus { status, body },
+        }
+    }
+
+    /// The UpCloud error code, if the API reported one.
+    pub fn code(&self) -> Option<&ErrorCode> {
+        match self {
+            Error::Api(api_error) => Some(&api_error.code),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status the API answered with, if it got that far.
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            Error::Transport(err) => err.status(),
+            Error::Decode { .. } => None,
+            Error::Api(api_error) => Some(api_error.status),
+            Error::UnexpectedStatus { status, .. } => Some(*status),
+            Error::Timeout(_) | Error::Cancelled => None,
+            Error::Io(_) | Error::ImportFailed { .. } | Error::ChecksumMismatch { .. } => None,
+            Error::NoDisk(_) | Error::NoPublicInterface(_) => None,
+            Error::UnexpectedAttachment { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Transport(err) => write!(f, "request failed: {}", err),
+            Error::Decode { source, body } => {
+                write!(f, "couldn't decode response ({}): {}", source, body)
+            }
+            Error::Api(api_error) => write!(f, "UpCloud API error: {}", api_error),
+            Error::UnexpectedStatus { status, body } => {
+                write!(f, "unexpected response status {}: {}", status, body)
+            }
+            Error::Timeout(waited) => write!(f, "gave up waiting after {:?}", waited),
+            Error::Cancelled => write!(f, "waiting was cancelled"),
+            Error::Io(err) => write!(f, "couldn't read file: {}", err),
+            Error::ImportFailed { code, message } => {
+                write!(f, "storage import failed: {} {}", code, message)
+            }
+            Error::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: sent data with SHA-256 {}, UpCloud got {}",
+                expected, actual
+            ),
+            Error::NoDisk(server) => write!(f, "server {} has no disk", server),
+            Error::NoPublicInterface(server) => {
+                write!(f, "server {} has no public network interface", server)
+            }
+            Error::UnexpectedAttachment {
+                address,
+                expected,
+                actual: Some(actual),
+            } => write!(
+                f,
+                "{} is attached to server {} instead of {}",
+                address, actual, expected
+            ),
+            Error::UnexpectedAttachment {
+                address, expected, ..
+            } => write!(f, "{} isn't attached to server {}", address, expected),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(err) => Some(err),
+            Error::Decode { source, .. } => Some(source),
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Error {
+        Error::Transport(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parses_api_error_payload() {
+        let body = String::from(
+            r#"{"error":{"error_code":"SERVER_NOT_FOUND","error_message":"The server 00798b85-efdc-41ca-8021-f6ef457b8531 does not exist."}}"#,
+        );
+        let err = Error::from_response(reqwest::StatusCode::NOT_FOUND, body);
+
+        assert_eq!(err.code(), Some(&ErrorCode::ServerNotFound));
+        assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn keeps_unknown_error_codes() {
+        let body = String::from(
+            r#"{"error":{"error_code":"SOMETHING_NEW","error_message":"Brand new failure."}}"#,
+        );
+        let err = Error::from_response(reqwest::StatusCode::BAD_REQUEST, body);
+
+        assert_eq!(
+            err.code(),
+            Some(&ErrorCode::Other(String::from("SOMETHING_NEW")))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_unexpected_status() {
+        let body = String::from("<html>Bad Gateway</html>");
+        let err = Error::from_response(reqwest::StatusCode::BAD_GATEWAY, body);
+
+        match err {
+            Error::UnexpectedStatus { status, body } => {
+                assert_eq!(status, reqwest::StatusCode::BAD_GATEWAY);
+                assert_eq!(body, "<html>Bad Gateway</html>");
+            }
+            other => panic!("Expected UnexpectedStatus, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,455 @@
+//! UpCloud's per-server firewall, which filters traffic before it reaches
+//! the guest.
+//!
+//! Rules are matched in order of `position`, and the first match decides.
+//! Traffic no rule matches is let through, so a rule set usually ends with a
+//! rule that drops everything else. The rules only apply while the server's
+//! `firewall` flag is on, see `firewall::set_enabled`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::request;
+use crate::server::{IpFamily, ServerDetails, ServerDetailsResponse};
+use crate::wire;
+use crate::{Context, Error};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallDirection {
+    In,
+    Out,
+    /// A direction this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallAction {
+    Accept,
+    /// Drops the packet and tells the sender.
+    Reject,
+    /// Drops the packet silently.
+    Drop,
+    /// An action this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallProtocol {
+    Tcp,
+    Udp,
+    Icmp,
+    /// A protocol this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+/// A firewall rule. Fields that are `None` match anything.
+///
+/// Port and address ranges are inclusive; a single port or address has the
+/// same start and end.
+///
+/// ```no_run
+/// # use cloudup::firewall::{FirewallAction, FirewallDirection, FirewallProtocol, FirewallRule};
+/// # use cloudup::server::IpFamily;
+/// let rule = FirewallRule::new(FirewallDirection::In, FirewallAction::Accept, IpFamily::IPv4)
+///     .protocol(FirewallProtocol::Tcp)
+///     .destination_port(25565, 25565)
+///     .comment(String::from("Minecraft"));
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct FirewallRule {
+    pub action: FirewallAction,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub comment: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub destination_address_end: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub destination_address_start: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub destination_port_end: Option<u16>,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub destination_port_start: Option<u16>,
+    pub direction: FirewallDirection,
+    pub family: IpFamily,
+    /// For `FirewallProtocol::Icmp`: the ICMP type, e.g. 8 for echo requests.
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub icmp_type: Option<u8>,
+    /// 1-based place of the rule in the list. `None` when creating appends
+    /// the rule to the end.
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub position: Option<u32>,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub protocol: Option<FirewallProtocol>,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub source_address_end: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub source_address_start: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub source_port_end: Option<u16>,
+    #[serde(
+        default,
+        with = "wire::optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub source_port_start: Option<u16>,
+}
+
+impl FirewallRule {
+    /// A rule matching all traffic of `family` in `direction`.
+    pub fn new(
+        direction: FirewallDirection,
+        action: FirewallAction,
+        family: IpFamily,
+    ) -> FirewallRule {
+        FirewallRule {
+            action,
+            comment: None,
+            destination_address_end: None,
+            destination_address_start: None,
+            destination_port_end: None,
+            destination_port_start: None,
+            direction,
+            family,
+            icmp_type: None,
+            position: None,
+            protocol: None,
+            source_address_end: None,
+            source_address_start: None,
+            source_port_end: None,
+            source_port_start: None,
+        }
+    }
+
+    pub fn protocol(mut self, protocol: FirewallProtocol) -> FirewallRule {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    pub fn destination_port(mut self, start: u16, end: u16) -> FirewallRule {
+        self.destination_port_start = Some(start);
+        self.destination_port_end = Some(end);
+        self
+    }
+
+    pub fn destination_address(mut self, start: String, end: String) -> FirewallRule {
+        self.destination_address_start = Some(start);
+        self.destination_address_end = Some(end);
+        self
+    }
+
+    pub fn source_port(mut self, start: u16, end: u16) -> FirewallRule {
+        self.source_port_start = Some(start);
+        self.source_port_end = Some(end);
+        self
+    }
+
+    pub fn source_address(mut self, start: String, end: String) -> FirewallRule {
+        self.source_address_start = Some(start);
+        self.source_address_end = Some(end);
+        self
+    }
+
+    pub fn icmp_type(mut self, icmp_type: u8) -> FirewallRule {
+        self.icmp_type = Some(icmp_type);
+        self
+    }
+
+    /// Inserts the rule at `position`, moving the rules after it down.
+    pub fn position(mut self, position: u32) -> FirewallRule {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn comment(mut self, comment: String) -> FirewallRule {
+        self.comment = Some(comment);
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct FirewallRulesField {
+    firewall_rule: Vec<FirewallRule>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct FirewallRulesPayload {
+    firewall_rules: FirewallRulesField,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct FirewallRuleResponse {
+    firewall_rule: FirewallRule,
+}
+
+#[derive(Serialize, Debug)]
+struct FirewallRulePayload<'a> {
+    firewall_rule: &'a FirewallRule,
+}
+
+#[derive(Serialize, Debug)]
+struct FirewallField {
+    #[serde(with = "wire::on_off")]
+    firewall: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct ModifyServerPayload {
+    server: FirewallField,
+}
+
+/// GET /1.3/server/{uuid}/firewall_rule -> Lists the rules of a server in
+/// order.
+pub async fn list(ctx: &Context, server_uuid: String) -> Result<Vec<FirewallRule>, Error> {
+    let response: FirewallRulesPayload =
+        request::get(ctx, &format!("/server/{}/firewall_rule", server_uuid)).await?;
+    Ok(response.firewall_rules.firewall_rule)
+}
+
+/// GET /1.3/server/{uuid}/firewall_rule/{position}
+pub async fn get(ctx: &Context, server_uuid: String, position: u32) -> Result<FirewallRule, Error> {
+    let response: FirewallRuleResponse = request::get(
+        ctx,
+        &format!("/server/{}/firewall_rule/{}", server_uuid, position),
+    )
+    .await?;
+    Ok(response.firewall_rule)
+}
+
+/// POST /1.3/server/{uuid}/firewall_rule -> Adds a rule at its `position`,
+/// or at the end without one.
+pub async fn create(
+    ctx: &Context,
+    server_uuid: String,
+    firewall_rule: &FirewallRule,
+) -> Result<FirewallRule, Error> {
+    let response: FirewallRuleResponse = request::post(
+        ctx,
+        &format!("/server/{}/firewall_rule", server_uuid),
+        &FirewallRulePayload { firewall_rule },
+    )
+    .await?;
+    Ok(response.firewall_rule)
+}
+
+/// DELETE /1.3/server/{uuid}/firewall_rule/{position} -> Removes a rule,
+/// moving the rules after it up.
+///
+/// Never retried, whatever the context's `RetryPolicy`: if the response got
+/// lost, a retry would delete the rule that moved into `position`. Check with
+/// `firewall::list` after a transport error.
+pub async fn delete(ctx: &Context, server_uuid: String, position: u32) -> Result<(), Error> {
+    request::delete_once(
+        ctx,
+        &format!("/server/{}/firewall_rule/{}", server_uuid, position),
+    )
+    .await
+}
+
+/// PUT /1.3/server/{uuid}/firewall_rule -> Replaces all the rules of a server
+/// with `rules`, in order. Their `position`s are ignored.
+///
+/// ```no_run
+/// # async fn example(ctx: cloudup::Context, server: String) -> Result<(), cloudup::Error> {
+/// use cloudup::firewall::{self, FirewallAction, FirewallDirection, FirewallProtocol, FirewallRule};
+/// use cloudup::server::IpFamily;
+///
+/// let rules = [
+///     FirewallRule::new(FirewallDirection::In, FirewallAction::Accept, IpFamily::IPv4)
+///         .protocol(FirewallProtocol::Tcp)
+///         .destination_port(25565, 25565),
+///     FirewallRule::new(FirewallDirection::In, FirewallAction::Drop, IpFamily::IPv4),
+/// ];
+/// firewall::replace_all(&ctx, server.clone(), &rules).await?;
+/// firewall::set_enabled(&ctx, server, true).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn replace_all(
+    ctx: &Context,
+    server_uuid: String,
+    rules: &[FirewallRule],
+) -> Result<(), Error> {
+    let firewall_rule = rules
+        .iter()
+        .cloned()
+        .map(|rule| FirewallRule {
+            position: None,
+            ..rule
+        })
+        .collect();
+    request::put_no_content(
+        ctx,
+        &format!("/server/{}/firewall_rule", server_uuid),
+        &FirewallRulesPayload {
+            firewall_rules: FirewallRulesField { firewall_rule },
+        },
+    )
+    .await
+}
+
+/// PUT /1.3/server/{uuid} -> Turns the server's firewall on or off. The
+/// rules are kept either way.
+pub async fn set_enabled(
+    ctx: &Context,
+    server_uuid: String,
+    enabled: bool,
+) -> Result<ServerDetails, Error> {
+    let response: ServerDetailsResponse = request::put(
+        ctx,
+        &format!("/server/{}", server_uuid),
+        &ModifyServerPayload {
+            server: FirewallField { firewall: enabled },
+        },
+    )
+    .await?;
+    Ok(response.server)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn firewall_rule_wire_format() {
+        let rule = FirewallRule::new(
+            FirewallDirection::In,
+            FirewallAction::Accept,
+            IpFamily::IPv4,
+        )
+        .protocol(FirewallProtocol::Tcp)
+        .destination_port(25565, 25565)
+        .comment(String::from("Minecraft"));
+
+        assert_eq!(
+            serde_json::to_value(FirewallRulePayload {
+                firewall_rule: &rule
+            })
+            .unwrap(),
+            json!({
+                "firewall_rule": {
+                    "action": "accept",
+                    "comment": "Minecraft",
+                    "destination_port_end": "25565",
+                    "destination_port_start": "25565",
+                    "direction": "in",
+                    "family": "IPv4",
+                    "protocol": "tcp"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn firewall_rule_details() {
+        let body = json!({
+            "firewall_rules": {
+                "firewall_rule": [
+                    {
+                        "action": "accept",
+                        "comment": "",
+                        "destination_address_end": "",
+                        "destination_address_start": "",
+                        "destination_port_end": "",
+                        "destination_port_start": "",
+                        "direction": "in",
+                        "family": "IPv4",
+                        "icmp_type": "8",
+                        "position": "1",
+                        "protocol": "icmp",
+                        "source_address_end": "192.168.1.255",
+                        "source_address_start": "192.168.1.1",
+                        "source_port_end": "",
+                        "source_port_start": ""
+                    }
+                ]
+            }
+        });
+
+        let rules = serde_json::from_value::<FirewallRulesPayload>(body)
+            .unwrap()
+            .firewall_rules
+            .firewall_rule;
+
+        assert_eq!(
+            rules,
+            vec![FirewallRule::new(
+                FirewallDirection::In,
+                FirewallAction::Accept,
+                IpFamily::IPv4
+            )
+            .protocol(FirewallProtocol::Icmp)
+            .icmp_type(8)
+            .source_address(String::from("192.168.1.1"), String::from("192.168.1.255"))
+            .position(1)]
+        );
+    }
+
+    #[test]
+    fn unknown_firewall_values() {
+        let rule: FirewallRule = serde_json::from_value(json!({
+            "action": "log",
+            "direction": "forward",
+            "family": "IPv4",
+            "protocol": "sctp"
+        }))
+        .unwrap();
+
+        assert_eq!(rule.action, FirewallAction::Unknown);
+        assert_eq!(rule.direction, FirewallDirection::Unknown);
+        assert_eq!(rule.protocol, Some(FirewallProtocol::Unknown));
+    }
+}
@@ -0,0 +1,145 @@
+//! Floating IP addresses, which can be moved between the servers of a zone
+//! to keep a DNS name pointing at whichever server is serving it.
+
+use serde::Serialize;
+
+use crate::ip_addresses::{
+    self, IpAddressInfo, IpAddressPayload, IpAddressRequest, IpAddressResponse,
+};
+use crate::request;
+use crate::server::{self, InterfaceType, ServerDetails};
+use crate::{Context, Error};
+
+#[derive(Serialize, Debug)]
+struct MacField<'a> {
+    /// `null` detaches the address.
+    mac: Option<&'a str>,
+}
+
+/// The account's floating addresses, attached or not.
+pub async fn list(ctx: &Context) -> Result<Vec<IpAddressInfo>, Error> {
+    let mut addresses = ip_addresses::list(ctx).await?;
+    addresses.retain(|address| address.floating == Some(true));
+    Ok(addresses)
+}
+
+/// POST /1.3/ip_address -> Creates a floating address in `zone` that isn't
+/// attached to any server yet.
+pub async fn create(ctx: &Context, zone: String) -> Result<IpAddressInfo, Error> {
+    ip_addresses::assign(ctx, &IpAddressRequest::floating(zone)).await
+}
+
+/// PUT /1.3/ip_address/{address} -> Moves a floating address to the network
+/// interface with the MAC address `mac`.
+pub async fn attach(ctx: &Context, address: String, mac: String) -> Result<IpAddressInfo, Error> {
+    modify_mac(ctx, address, Some(&mac)).await
+}
+
+/// PUT /1.3/ip_address/{address} -> Detaches a floating address, keeping it
+/// on the account.
+pub async fn detach(ctx: &Context, address: String) -> Result<IpAddressInfo, Error> {
+    modify_mac(ctx, address, None).await
+}
+
+async fn modify_mac(
+    ctx: &Context,
+    address: String,
+    mac: Option<&str>,
+) -> Result<IpAddressInfo, Error> {
+    let response: IpAddressResponse = request::put(
+        ctx,
+        &format!("/ip_address/{}", address),
+        &IpAddressPayload {
+            ip_address: &MacField { mac },
+        },
+    )
+    .await?;
+    Ok(response.ip_address)
+}
+
+/// MAC address of the first public network interface of the server.
+pub fn public_mac(server: &ServerDetails) -> Option<&str> {
+    server
+        .interfaces()
+        .iter()
+        .find(|interface| interface.interface_type == InterfaceType::Public)
+        .map(|interface| interface.mac.as_str())
+}
+
+/// Moves a floating address from `from_server` to the public interface of
+/// `to_server`, then checks with UpCloud that both the address and the
+/// server agree it's there.
+///
+/// An address that's already detached, as happens when `from_server` is
+/// deleted, is moved too. One attached to any other server isn't touched,
+/// and neither is one already on `to_server`.
+///
+/// ```no_run
+/// # async fn example(ctx: cloudup::Context, old: String, new: String) -> Result<(), cloudup::Error> {
+/// use cloudup::floating_ip;
+///
+/// floating_ip::failover(&ctx, String::from("94.237.0.5"), old, new).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn failover(
+    ctx: &Context,
+    address: String,
+    from_server: String,
+    to_server: String,
+) -> Result<IpAddressInfo, Error> {
+    let current = ip_addresses::get(ctx, address.clone()).await?;
+    match current.server {
+        Some(ref server) if *server == to_server => return Ok(current),
+        Some(ref server) if *server != from_server => {
+            return Err(Error::UnexpectedAttachment {
+                address,
+                expected: from_server,
+                actual: current.server,
+            })
+        }
+        _ => {}
+    }
+
+    let target = server::get_server(ctx, to_server.clone()).await?;
+    let mac = public_mac(&target)
+        .ok_or_else(|| Error::NoPublicInterface(to_server.clone()))?
+        .to_string();
+    attach(ctx, address.clone(), mac.clone()).await?;
+
+    let moved = ip_addresses::get(ctx, address.clone()).await?;
+    let target = server::get_server(ctx, to_server.clone()).await?;
+    let listed = target
+        .interfaces()
+        .iter()
+        .filter(|interface| interface.mac == mac)
+        .flat_map(|interface| &interface.ip_addresses.ip_address)
+        .any(|ip| ip.address == address);
+    if moved.server.as_ref() != Some(&to_server) || !listed {
+        return Err(Error::UnexpectedAttachment {
+            address,
+            expected: to_server,
+            actual: moved.server,
+        });
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn detach_wire_format() {
+        let body = IpAddressPayload {
+            ip_address: &MacField { mac: None },
+        };
+
+        assert_eq!(
+            serde_json::to_value(body).unwrap(),
+            json!({ "ip_address": { "mac": null } })
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+
+use crate::request;
+use crate::server::{AccessType, IpAddressesField, IpFamily};
+use crate::wire;
+use crate::{Context, Error};
+
+pub use crate::server::IpAddressInfo;
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct GetIpAddressesResponse {
+    ip_addresses: IpAddressesField,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub(crate) struct IpAddressResponse {
+    pub(crate) ip_address: IpAddressInfo,
+}
+
+/// Describes an address to add with `ip_addresses::assign`.
+///
+/// ```no_run
+/// # use cloudup::ip_addresses::IpAddressRequest;
+/// # use cloudup::server::{AccessType, IpFamily};
+/// let request = IpAddressRequest::new(
+///     String::from("0077fa3d-32db-4b09-9f5f-30d9e9afb565"),
+///     IpFamily::IPv4,
+/// )
+/// .access(AccessType::Utility);
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IpAddressRequest {
+    access: AccessType,
+    family: IpFamily,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    floating: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    server: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zone: Option<String>,
+}
+
+impl IpAddressRequest {
+    /// A public address of `family` for the server `server_uuid`.
+    pub fn new(server_uuid: String, family: IpFamily) -> IpAddressRequest {
+        IpAddressRequest {
+            access: AccessType::Public,
+            family,
+            floating: None,
+            mac: None,
+            server: Some(server_uuid),
+            zone: None,
+        }
+    }
+
+    /// `AccessType::Utility` for an address on the utility network, or
+    /// `AccessType::Private` for one on a private network.
+    pub fn access(mut self, access: AccessType) -> IpAddressRequest {
+        self.access = access;
+        self
+    }
+
+    /// A floating IPv4 address in `zone`, which can be moved between the
+    /// servers of the zone. It isn't attached anywhere unless `mac` is given.
+    pub fn floating(zone: String) -> IpAddressRequest {
+        IpAddressRequest {
+            access: AccessType::Public,
+            family: IpFamily::IPv4,
+            floating: Some(true),
+            mac: None,
+            server: None,
+            zone: Some(zone),
+        }
+    }
+
+    /// For floating addresses: the MAC address of the public interface to
+    /// attach to.
+    pub fn mac(mut self, mac: String) -> IpAddressRequest {
+        self.mac = Some(mac);
+        self
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct IpAddressPayload<'a, T> {
+    pub(crate) ip_address: &'a T,
+}
+
+#[derive(Serialize, Debug)]
+struct PtrRecordField<'a> {
+    ptr_record: &'a str,
+}
+
+/// GET /1.3/ip_address -> Lists the addresses of every server on the account.
+pub async fn list(ctx: &Context) -> Result<Vec<IpAddressInfo>, Error> {
+    let response: GetIpAddressesResponse = request::get(ctx, "/ip_address").await?;
+    Ok(response.ip_addresses.ip_address)
+}
+
+/// GET /1.3/ip_address/{address}
+pub async fn get(ctx: &Context, address: String) -> Result<IpAddressInfo, Error> {
+    let response: IpAddressResponse =
+        request::get(ctx, &format!("/ip_address/{}", address)).await?;
+    Ok(response.ip_address)
+}
+
+/// POST /1.3/ip_address -> Adds an address to a server, or creates a
+/// floating one.
+///
+/// The server needs a restart, or the guest a network reconfiguration, to
+/// start using it.
+pub async fn assign(ctx: &Context, ip_address: &IpAddressRequest) -> Result<IpAddressInfo, Error> {
+    let response: IpAddressResponse =
+        request::post(ctx, "/ip_address", &IpAddressPayload { ip_address }).await?;
+    Ok(response.ip_address)
+}
+
+/// PUT /1.3/ip_address/{address} -> Sets the reverse DNS name of a public address.
+pub async fn set_ptr_record(
+    ctx: &Context,
+    address: String,
+    ptr_record: String,
+) -> Result<IpAddressInfo, Error> {
+    let response: IpAddressResponse = request::put(
+        ctx,
+        &format!("/ip_address/{}", address),
+        &IpAddressPayload {
+            ip_address: &PtrRecordField {
+                ptr_record: &ptr_record,
+            },
+        },
+    )
+    .await?;
+    Ok(response.ip_address)
+}
+
+/// DELETE /1.3/ip_address/{address} -> Removes the address from its server
+/// and gives it back to UpCloud.
+pub async fn release(ctx: &Context, address: String) -> Result<(), Error> {
+    request::delete(ctx, &format!("/ip_address/{}", address)).await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn floating_request_wire_format() {
+        let request = IpAddressRequest::floating(String::from("fi-hel1"))
+            .mac(String::from("de:ff:ff:ff:66:89"));
+
+        assert_eq!(
+            serde_json::to_value(IpAddressPayload {
+                ip_address: &request
+            })
+            .unwrap(),
+            json!({
+                "ip_address": {
+                    "access": "public",
+                    "family": "IPv4",
+                    "floating": "yes",
+                    "mac": "de:ff:ff:ff:66:89",
+                    "zone": "fi-hel1"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn ip_address_details() {
+        let body = json!({
+            "ip_address": {
+                "access": "public",
+                "address": "94.237.12.34",
+                "family": "IPv4",
+                "floating": "no",
+                "mac": "de:ff:ff:ff:66:89",
+                "part_of_plan": "yes",
+                "ptr_record": "94-237-12-34.fi-hel1.upcloud.host",
+                "server": "0077fa3d-32db-4b09-9f5f-30d9e9afb565",
+                "zone": "fi-hel1"
+            }
+        });
+
+        let address = serde_json::from_value::<IpAddressResponse>(body)
+            .unwrap()
+            .ip_address;
+
+        assert_eq!(address.floating, Some(false));
+        assert_eq!(address.part_of_plan, Some(true));
+        assert_eq!(
+            address.server.as_deref(),
+            Some("0077fa3d-32db-4b09-9f5f-30d9e9afb565")
+        );
+    }
+}
@@ -1,28 +1,23 @@
 pub mod accounts;
+pub mod backups;
+mod context;
+pub mod error;
+pub mod firewall;
+pub mod floating_ip;
+pub mod ip_addresses;
+pub mod networks;
+pub mod permissions;
 pub mod plan;
 pub mod pricing;
+mod request;
+pub mod retry;
 pub mod server;
 pub mod storage;
+pub mod wait;
+mod wire;
 pub mod zones;
 
-struct Credentials {
-    user: String,
-    pass: Option<String>,
-}
-
-pub struct Context {
-    uc_baseurl: &'static str,
-    credentials: Credentials,
-}
-
-impl Context {
-    pub fn new(endpoint: &'static str, user: String, password: String) -> Context {
-        return Context {
-            uc_baseurl: endpoint,
-            credentials: Credentials {
-                user,
-                pass: Some(password),
-            },
-        };
-    }
-}
+pub use context::{Context, ContextBuilder, DEFAULT_BASEURL};
+pub use error::Error;
+pub use retry::RetryPolicy;
+pub use wait::WaitOptions;
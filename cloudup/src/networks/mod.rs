@@ -0,0 +1,386 @@
+//! Private SDN networks and the routers that connect them.
+//!
+//! Servers join a network with `server::interfaces::add` and an
+//! `IpAddrTypePair::private` interface.
+
+use serde::{Deserialize, Serialize};
+
+use crate::request;
+use crate::server::IpFamily;
+use crate::wire;
+use crate::{Context, Error};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkType {
+    Public,
+    Utility,
+    Private,
+    /// A network type this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+/// An address range of a network and how DHCP hands out addresses in it.
+///
+/// ```no_run
+/// # use cloudup::networks::IpNetwork;
+/// # use cloudup::server::IpFamily;
+/// let range = IpNetwork::new(String::from("172.16.0.0/24"), IpFamily::IPv4)
+///     .gateway(String::from("172.16.0.1"))
+///     .dhcp_dns(vec![String::from("94.237.127.9")]);
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct IpNetwork {
+    /// The range in CIDR notation, e.g. `172.16.0.0/24`.
+    pub address: String,
+    #[serde(with = "wire::yes_no")]
+    pub dhcp: bool,
+    /// Whether DHCP makes the gateway the default route.
+    #[serde(with = "wire::yes_no")]
+    pub dhcp_default_route: bool,
+    /// Name servers handed out by DHCP.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dhcp_dns: Vec<String>,
+    pub family: IpFamily,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<String>,
+}
+
+impl IpNetwork {
+    /// A range with DHCP on and no default route.
+    pub fn new(address: String, family: IpFamily) -> IpNetwork {
+        IpNetwork {
+            address,
+            dhcp: true,
+            dhcp_default_route: false,
+            dhcp_dns: vec![],
+            family,
+            gateway: None,
+        }
+    }
+
+    pub fn dhcp(mut self, dhcp: bool) -> IpNetwork {
+        self.dhcp = dhcp;
+        self
+    }
+
+    pub fn dhcp_default_route(mut self, dhcp_default_route: bool) -> IpNetwork {
+        self.dhcp_default_route = dhcp_default_route;
+        self
+    }
+
+    pub fn dhcp_dns(mut self, dhcp_dns: Vec<String>) -> IpNetwork {
+        self.dhcp_dns = dhcp_dns;
+        self
+    }
+
+    pub fn gateway(mut self, gateway: String) -> IpNetwork {
+        self.gateway = Some(gateway);
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct IpNetworksField {
+    pub ip_network: Vec<IpNetwork>,
+}
+
+/// A server on a network.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct NetworkServer {
+    pub title: String,
+    pub uuid: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct NetworkServersField {
+    pub server: Vec<NetworkServer>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct Network {
+    pub ip_networks: IpNetworksField,
+    pub name: String,
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    pub network_type: NetworkType,
+    /// UUID of the router the network is attached to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub router: Option<String>,
+    /// Only filled in by `networks::get`.
+    #[serde(default)]
+    pub servers: NetworkServersField,
+    pub uuid: String,
+    pub zone: String,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct GetNetworksResponseNetworksField {
+    network: Vec<Network>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct GetNetworksResponse {
+    networks: GetNetworksResponseNetworksField,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct NetworkResponse {
+    network: Network,
+}
+
+/// Describes a private network to create with `networks::create`.
+///
+/// ```no_run
+/// # use cloudup::networks::{IpNetwork, NetworkCreateRequest};
+/// # use cloudup::server::IpFamily;
+/// let request = NetworkCreateRequest::new(
+///     String::from("Backend"),
+///     String::from("fi-hel1"),
+///     IpNetwork::new(String::from("172.16.0.0/24"), IpFamily::IPv4),
+/// );
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkCreateRequest {
+    ip_networks: IpNetworksField,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    router: Option<String>,
+    zone: String,
+}
+
+impl NetworkCreateRequest {
+    pub fn new(name: String, zone: String, ip_network: IpNetwork) -> NetworkCreateRequest {
+        NetworkCreateRequest {
+            ip_networks: IpNetworksField {
+                ip_network: vec![ip_network],
+            },
+            name,
+            router: None,
+            zone,
+        }
+    }
+
+    /// Adds another address range, such as an IPv6 one.
+    pub fn ip_network(mut self, ip_network: IpNetwork) -> NetworkCreateRequest {
+        self.ip_networks.ip_network.push(ip_network);
+        self
+    }
+
+    /// Attaches the network to a router right away.
+    pub fn router(mut self, router_uuid: String) -> NetworkCreateRequest {
+        self.router = Some(router_uuid);
+        self
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct NetworkPayload<'a, T> {
+    network: &'a T,
+}
+
+#[derive(Serialize, Debug)]
+struct RouterField<'a> {
+    /// `null` detaches the network from its router.
+    router: Option<&'a str>,
+}
+
+/// GET /1.3/network -> Lists the public, utility and private networks the
+/// account can use.
+pub async fn list(ctx: &Context) -> Result<Vec<Network>, Error> {
+    let response: GetNetworksResponse = request::get(ctx, "/network").await?;
+    Ok(response.networks.network)
+}
+
+/// GET /1.3/network/?zone={zone}
+pub async fn list_in_zone(ctx: &Context, zone: String) -> Result<Vec<Network>, Error> {
+    let response: GetNetworksResponse =
+        request::get(ctx, &format!("/network/?zone={}", zone)).await?;
+    Ok(response.networks.network)
+}
+
+/// GET /1.3/network/{uuid}
+pub async fn get(ctx: &Context, uuid: String) -> Result<Network, Error> {
+    let response: NetworkResponse = request::get(ctx, &format!("/network/{}", uuid)).await?;
+    Ok(response.network)
+}
+
+/// POST /1.3/network -> Creates a private network.
+pub async fn create(ctx: &Context, network: &NetworkCreateRequest) -> Result<Network, Error> {
+    let response: NetworkResponse =
+        request::post(ctx, "/network", &NetworkPayload { network }).await?;
+    Ok(response.network)
+}
+
+/// DELETE /1.3/network/{uuid} -> Deletes a private network. No server may
+/// have an interface in it.
+pub async fn delete(ctx: &Context, uuid: String) -> Result<(), Error> {
+    request::delete(ctx, &format!("/network/{}", uuid)).await
+}
+
+/// PUT /1.3/network/{uuid} -> Attaches a network to a router, detaching it
+/// from any other.
+pub async fn attach_router(
+    ctx: &Context,
+    uuid: String,
+    router_uuid: String,
+) -> Result<Network, Error> {
+    modify_router(ctx, uuid, Some(&router_uuid)).await
+}
+
+/// PUT /1.3/network/{uuid} -> Detaches a network from its router.
+pub async fn detach_router(ctx: &Context, uuid: String) -> Result<Network, Error> {
+    modify_router(ctx, uuid, None).await
+}
+
+async fn modify_router(
+    ctx: &Context,
+    uuid: String,
+    router: Option<&str>,
+) -> Result<Network, Error> {
+    let response: NetworkResponse = request::put(
+        ctx,
+        &format!("/network/{}", uuid),
+        &NetworkPayload {
+            network: &RouterField { router },
+        },
+    )
+    .await?;
+    Ok(response.network)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct AttachedNetwork {
+    pub uuid: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct AttachedNetworksField {
+    pub network: Vec<AttachedNetwork>,
+}
+
+/// Routes traffic between the private networks attached to it.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct Router {
+    #[serde(default)]
+    pub attached_networks: AttachedNetworksField,
+    pub name: String,
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    pub router_type: String,
+    pub uuid: String,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct GetRoutersResponseRoutersField {
+    router: Vec<Router>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct GetRoutersResponse {
+    routers: GetRoutersResponseRoutersField,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct RouterResponse {
+    router: Router,
+}
+
+#[derive(Serialize, Debug)]
+struct RouterPayload<'a> {
+    router: NameField<'a>,
+}
+
+#[derive(Serialize, Debug)]
+struct NameField<'a> {
+    name: &'a str,
+}
+
+/// GET /1.3/router
+pub async fn list_routers(ctx: &Context) -> Result<Vec<Router>, Error> {
+    let response: GetRoutersResponse = request::get(ctx, "/router").await?;
+    Ok(response.routers.router)
+}
+
+/// GET /1.3/router/{uuid}
+pub async fn get_router(ctx: &Context, uuid: String) -> Result<Router, Error> {
+    let response: RouterResponse = request::get(ctx, &format!("/router/{}", uuid)).await?;
+    Ok(response.router)
+}
+
+/// POST /1.3/router -> Creates a router with no networks attached.
+pub async fn create_router(ctx: &Context, name: String) -> Result<Router, Error> {
+    let body = RouterPayload {
+        router: NameField { name: &name },
+    };
+    let response: RouterResponse = request::post(ctx, "/router", &body).await?;
+    Ok(response.router)
+}
+
+/// DELETE /1.3/router/{uuid} -> Deletes a router. No network may be
+/// attached to it.
+pub async fn delete_router(ctx: &Context, uuid: String) -> Result<(), Error> {
+    request::delete(ctx, &format!("/router/{}", uuid)).await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn create_request_wire_format() {
+        let request = NetworkCreateRequest::new(
+            String::from("Backend"),
+            String::from("fi-hel1"),
+            IpNetwork::new(String::from("172.16.0.0/24"), IpFamily::IPv4)
+                .gateway(String::from("172.16.0.1"))
+                .dhcp_dns(vec![String::from("94.237.127.9")]),
+        )
+        .router(String::from("04c0e4aa-8c9c-4a6e-a2b6-8a7c3b8cf5b8"));
+
+        assert_eq!(
+            serde_json::to_value(NetworkPayload { network: &request }).unwrap(),
+            json!({
+                "network": {
+                    "ip_networks": {
+                        "ip_network": [{
+                            "address": "172.16.0.0/24",
+                            "dhcp": "yes",
+                            "dhcp_default_route": "no",
+                            "dhcp_dns": ["94.237.127.9"],
+                            "family": "IPv4",
+                            "gateway": "172.16.0.1"
+                        }]
+                    },
+                    "name": "Backend",
+                    "router": "04c0e4aa-8c9c-4a6e-a2b6-8a7c3b8cf5b8",
+                    "zone": "fi-hel1"
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::request;
+use crate::server;
+use crate::wire;
+use crate::{Context, Error};
+
+/// The kind of resource a permission is for.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetType {
+    Server,
+    Storage,
+    Network,
+    Router,
+    Tag,
+    /// A resource type this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct PermissionOptions {
+    /// For servers: whether the storages of the server can be used as well.
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub storage: Option<bool>,
+}
+
+/// Access of a sub-account to a single resource.
+///
+/// ```no_run
+/// # use cloudup::permissions::Permission;
+/// let permission = Permission::server(
+///     String::from("ci"),
+///     String::from("0077fa3d-32db-4b09-9f5f-30d9e9afb565"),
+/// )
+/// .with_storage(true);
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct Permission {
+    #[serde(default)]
+    pub options: PermissionOptions,
+    /// UUID of the resource, or the name of the tag.
+    pub target_identifier: String,
+    pub target_type: TargetType,
+    /// The sub-account the permission is granted to.
+    pub user: String,
+}
+
+impl Permission {
+    pub fn new(user: String, target_type: TargetType, target_identifier: String) -> Permission {
+        Permission {
+            options: PermissionOptions::default(),
+            target_identifier,
+            target_type,
+            user,
+        }
+    }
+
+    pub fn server(user: String, uuid: String) -> Permission {
+        Permission::new(user, TargetType::Server, uuid)
+    }
+
+    pub fn storage(user: String, uuid: String) -> Permission {
+        Permission::new(user, TargetType::Storage, uuid)
+    }
+
+    pub fn network(user: String, uuid: String) -> Permission {
+        Permission::new(user, TargetType::Network, uuid)
+    }
+
+    pub fn tag(user: String, tag: String) -> Permission {
+        Permission::new(user, TargetType::Tag, tag)
+    }
+
+    /// Lets the sub-account use the storages of a server as well.
+    pub fn with_storage(mut self, storage: bool) -> Permission {
+        self.options.storage = Some(storage);
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct PermissionsField {
+    permission: Vec<Permission>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct GetPermissionsResponse {
+    permissions: PermissionsField,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct PermissionPayload {
+    permission: Permission,
+}
+
+#[derive(Serialize, Debug)]
+struct PermissionRequest<'a> {
+    permission: &'a Permission,
+}
+
+/// GET /1.3/permission -> Lists the permissions granted to every sub-account.
+pub async fn list(ctx: &Context) -> Result<Vec<Permission>, Error> {
+    let response: GetPermissionsResponse = request::get(ctx, "/permission").await?;
+    Ok(response.permissions.permission)
+}
+
+/// The permissions granted to the sub-account `user`.
+pub async fn list_for(ctx: &Context, user: String) -> Result<Vec<Permission>, Error> {
+    let mut permissions = list(ctx).await?;
+    permissions.retain(|permission| permission.user == user);
+    Ok(permissions)
+}
+
+/// POST /1.3/permission/grant
+pub async fn grant(ctx: &Context, permission: &Permission) -> Result<Permission, Error> {
+    let response: PermissionPayload =
+        request::post(ctx, "/permission/grant", &PermissionRequest { permission }).await?;
+    Ok(response.permission)
+}
+
+/// POST /1.3/permission/revoke
+pub async fn revoke(ctx: &Context, permission: &Permission) -> Result<(), Error> {
+    request::post_no_content(ctx, "/permission/revoke", &PermissionRequest { permission }).await
+}
+
+/// What `scope_to_tag` changed.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeChanges {
+    pub granted: Vec<Permission>,
+    pub revoked: Vec<Permission>,
+}
+
+/// Limits the server permissions of `user` to exactly the servers tagged with
+/// `tag`: grants the ones missing and revokes the rest.
+///
+/// `storage` controls whether the storages of those servers can be used too.
+/// Permissions to other kinds of resources are left alone.
+pub async fn scope_to_tag(
+    ctx: &Context,
+    user: String,
+    tag: String,
+    storage: bool,
+) -> Result<ScopeChanges, Error> {
+    let tagged: BTreeSet<String> = server::get_servers(ctx)
+        .await?
+        .into_iter()
+        .filter(|server| server.tags.tag.contains(&tag))
+        .map(|server| server.uuid)
+        .collect();
+    let current: Vec<Permission> = list_for(ctx, user.clone())
+        .await?
+        .into_iter()
+        .filter(|permission| permission.target_type == TargetType::Server)
+        .collect();
+
+    let wanted = |permission: &Permission| {
+        tagged.contains(&permission.target_identifier)
+            && permission.options.storage.unwrap_or(false) == storage
+    };
+
+    let mut changes = ScopeChanges::default();
+    for permission in current.iter().filter(|permission| !wanted(permission)) {
+        revoke(ctx, permission).await?;
+        changes.revoked.push(permission.clone());
+    }
+    for uuid in &tagged {
+        let granted = current
+            .iter()
+            .any(|permission| &permission.target_identifier == uuid && wanted(permission));
+        if !granted {
+            let permission = Permission::server(user.clone(), uuid.clone()).with_storage(storage);
+            changes.granted.push(grant(ctx, &permission).await?);
+        }
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn permission_wire_format() {
+        let permission =
+            Permission::server(String::from("ci"), String::from("uuid")).with_storage(false);
+
+        assert_eq!(
+            serde_json::to_value(&PermissionRequest {
+                permission: &permission
+            })
+            .unwrap(),
+            json!({
+                "permission": {
+                    "options": { "storage": "no" },
+                    "target_identifier": "uuid",
+                    "target_type": "server",
+                    "user": "ci"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn tolerates_missing_options() {
+        let permission: Permission = serde_json::from_value(json!({
+            "target_identifier": "CI",
+            "target_type": "tag",
+            "user": "ci"
+        }))
+        .unwrap();
+
+        assert_eq!(
+            permission,
+            Permission::tag(String::from("ci"), String::from("CI"))
+        );
+    }
+}
@@ -1,47 +1,41 @@
 use serde::{Deserialize, Serialize};
 
-use crate::Context;
+use crate::request;
+use crate::storage::StorageTier;
+use crate::{Context, Error};
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Plan {
-    core_number: usize,
-    memory_amount: usize,
+    pub core_number: usize,
+    /// Memory in megabytes.
+    pub memory_amount: usize,
     pub name: String,
-    public_traffic_out: usize,
+    /// Included outbound traffic in gigabytes per month.
+    pub public_traffic_out: usize,
+    /// Size of the included storage in gigabytes.
     pub storage_size: usize,
-    storage_tier: String,
+    pub storage_tier: StorageTier,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct GetPlansResponsePlansField {
     plan: Vec<Plan>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct GetPlansResponse {
     plans: GetPlansResponsePlansField,
 }
 
-pub async fn get_plans(ctx: &Context) -> Result<Vec<Plan>, reqwest::Error> {
-    let url = format!("{}/1.3/plan", ctx.uc_baseurl);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .get(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let response: Result<GetPlansResponse, _> = match request {
-        Ok(response) => response.json::<GetPlansResponse>().await,
-        Err(error) => {
-            panic!("{}", error);
-        }
-    };
-
-    return match response {
-        Ok(payload) => Ok(payload.plans.plan),
-        Err(err) => panic!("{}", err),
-    };
+/// GET /1.3/plan -> Lists the available server plans.
+pub async fn get_plans(ctx: &Context) -> Result<Vec<Plan>, Error> {
+    let response: GetPlansResponse = request::get(ctx, "/plan").await?;
+    Ok(response.plans.plan)
 }
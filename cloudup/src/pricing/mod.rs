@@ -1,10 +1,11 @@
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-use crate::Context;
+use crate::request;
+use crate::{Context, Error};
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct PriceAmountPair {
     amount: usize,
     price: Decimal,
@@ -30,37 +31,26 @@ pub struct PricingZone {
     storage_hdd: PriceAmountPair,
     storage_maxiops: PriceAmountPair,
     storage_template: PriceAmountPair,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct PricesField {
     zone: Vec<PricingZone>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct GetPricingResponse {
     prices: PricesField,
 }
 
-pub async fn get_pricing(ctx: &Context) -> Result<Vec<PricingZone>, reqwest::Error> {
-    let url = format!("{}/1.3/price", ctx.uc_baseurl);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .get(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let response: Result<GetPricingResponse, _> = match request {
-        Ok(response) => response.json::<GetPricingResponse>().await,
-        Err(error) => {
-            panic!("{}", error);
-        }
-    };
-
-    return match response {
-        Ok(payload) => Ok(payload.prices.zone),
-        Err(err) => panic!("{}", err),
-    };
+/// GET /1.3/price -> Lists the prices of resources in every zone.
+pub async fn get_pricing(ctx: &Context) -> Result<Vec<PricingZone>, Error> {
+    let response: GetPricingResponse = request::get(ctx, "/price").await?;
+    Ok(response.prices.zone)
 }
@@ -0,0 +1,162 @@
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::retry;
+use crate::{Context, Error};
+
+const API_VERSION: &str = "1.3";
+
+fn build(ctx: &Context, method: Method, path: &str) -> RequestBuilder {
+    let url = format!("{}/{}{}", ctx.uc_baseurl, API_VERSION, path);
+    ctx.client
+        .request(method, &url)
+        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
+}
+
+/// Sends the request, retrying it as the context's `RetryPolicy` allows, and
+/// turns any non-successful status into an `Error`.
+async fn send<B: Serialize>(
+    ctx: &Context,
+    method: Method,
+    path: &str,
+    body: Option<&B>,
+) -> Result<Response, Error> {
+    let retries_allowed = ctx.retry_policy.applies_to(&method);
+    send_with(ctx, method, path, body, retries_allowed).await
+}
+
+async fn send_with<B: Serialize>(
+    ctx: &Context,
+    method: Method,
+    path: &str,
+    body: Option<&B>,
+    retries_allowed: bool,
+) -> Result<Response, Error> {
+    let policy = &ctx.retry_policy;
+    let mut attempt = 1;
+    loop {
+        let mut request = build(ctx, method.clone(), path);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let may_retry = retries_allowed && attempt < policy.max_attempts;
+
+        let delay = match request.send().await {
+            Ok(response) => {
+                let status = response.status();
+                if status.is_success() {
+                    return Ok(response);
+                }
+                if !(may_retry && retry::is_retryable_status(status)) {
+                    let body = response.text().await?;
+                    return Err(Error::from_response(status, body));
+                }
+                policy.delay_after(&response, attempt)
+            }
+            Err(err) => {
+                if !(may_retry && retry::is_transient(&err)) {
+                    return Err(Error::Transport(err));
+                }
+                policy.backoff(attempt)
+            }
+        };
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+async fn decode<T: DeserializeOwned>(response: Response) -> Result<T, Error> {
+    let body = response.text().await?;
+    serde_json::from_str(&body).map_err(|source| Error::Decode { source, body })
+}
+
+pub(crate) async fn get<T: DeserializeOwned>(ctx: &Context, path: &str) -> Result<T, Error> {
+    let response = send(ctx, Method::GET, path, None::<&()>).await?;
+    decode(response).await
+}
+
+pub(crate) async fn post<B: Serialize, T: DeserializeOwned>(
+    ctx: &Context,
+    path: &str,
+    body: &B,
+) -> Result<T, Error> {
+    let response = send(ctx, Method::POST, path, Some(body)).await?;
+    decode(response).await
+}
+
+pub(crate) async fn post_empty<T: DeserializeOwned>(ctx: &Context, path: &str) -> Result<T, Error> {
+    let response = send(ctx, Method::POST, path, None::<&()>).await?;
+    decode(response).await
+}
+
+/// For POSTs UpCloud answers with `204 No Content`.
+pub(crate) async fn post_no_content<B: Serialize>(
+    ctx: &Context,
+    path: &str,
+    body: &B,
+) -> Result<(), Error> {
+    send(ctx, Method::POST, path, Some(body)).await?;
+    Ok(())
+}
+
+/// For POSTs without a body that UpCloud answers with `204 No Content`.
+pub(crate) async fn post_empty_no_content(ctx: &Context, path: &str) -> Result<(), Error> {
+    send(ctx, Method::POST, path, None::<&()>).await?;
+    Ok(())
+}
+
+pub(crate) async fn put<B: Serialize, T: DeserializeOwned>(
+    ctx: &Context,
+    path: &str,
+    body: &B,
+) -> Result<T, Error> {
+    let response = send(ctx, Method::PUT, path, Some(body)).await?;
+    decode(response).await
+}
+
+/// For PUTs UpCloud answers with `204 No Content`.
+pub(crate) async fn put_no_content<B: Serialize>(
+    ctx: &Context,
+    path: &str,
+    body: &B,
+) -> Result<(), Error> {
+    send(ctx, Method::PUT, path, Some(body)).await?;
+    Ok(())
+}
+
+/// PUTs `body` to `url`, outside of the API, such as a storage import's
+/// upload URL. Not retried, as the body can only be read once.
+pub(crate) async fn upload(
+    ctx: &Context,
+    url: &str,
+    content_type: &str,
+    body: reqwest::Body,
+) -> Result<(), Error> {
+    let response = ctx
+        .client
+        .put(url)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(body)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await?;
+        return Err(Error::from_response(status, body));
+    }
+    Ok(())
+}
+
+pub(crate) async fn delete(ctx: &Context, path: &str) -> Result<(), Error> {
+    send(ctx, Method::DELETE, path, None::<&()>).await?;
+    Ok(())
+}
+
+/// For DELETEs that aren't idempotent, such as deleting by position, where a
+/// retry could remove whatever took the deleted item's place. Never retried.
+pub(crate) async fn delete_once(ctx: &Context, path: &str) -> Result<(), Error> {
+    send_with(ctx, Method::DELETE, path, None::<&()>, false).await?;
+    Ok(())
+}
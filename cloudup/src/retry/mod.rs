@@ -0,0 +1,216 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Utc};
+use reqwest::{Method, Response, StatusCode};
+
+/// How failed requests are retried.
+///
+/// Requests are retried when UpCloud answers `429 Too Many Requests` or
+/// `503 Service Unavailable`, or when the connection fails or times out.
+/// A `Retry-After` header, in seconds or as an HTTP date, overrides the
+/// computed backoff, though never beyond `max_backoff`.
+///
+/// Reads and deletes (GET, DELETE) are retried by default. POSTs create
+/// things, so a retried POST might create them twice; enable `retry_posts` to
+/// opt in. PUTs are idempotent in HTTP terms, but a PUT that UpCloud applied
+/// only in part, such as replacing a server's firewall rules, isn't safe to
+/// send again; enable `retry_puts` to opt in.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry. Doubles on every attempt after that.
+    pub initial_backoff: Duration,
+    /// Upper bound for any delay, including one asked for with `Retry-After`.
+    pub max_backoff: Duration,
+    /// Randomizes each delay to somewhere between half and all of it.
+    pub jitter: bool,
+    pub retry_posts: bool,
+    pub retry_puts: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+            retry_posts: false,
+            retry_puts: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retries anything.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> RetryPolicy {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> RetryPolicy {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> RetryPolicy {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn retry_posts(mut self, retry_posts: bool) -> RetryPolicy {
+        self.retry_posts = retry_posts;
+        self
+    }
+
+    pub fn retry_puts(mut self, retry_puts: bool) -> RetryPolicy {
+        self.retry_puts = retry_puts;
+        self
+    }
+
+    pub(crate) fn applies_to(&self, method: &Method) -> bool {
+        match *method {
+            Method::GET | Method::DELETE | Method::HEAD => true,
+            Method::POST => self.retry_posts,
+            Method::PUT => self.retry_puts,
+            _ => false,
+        }
+    }
+
+    /// The delay before retrying after `response` to the given (1-based)
+    /// attempt.
+    pub(crate) fn delay_after(&self, response: &Response, attempt: u32) -> Duration {
+        match retry_after(response, DateTime::from(SystemTime::now())) {
+            Some(delay) => delay.min(self.max_backoff),
+            None => self.backoff(attempt),
+        }
+    }
+
+    /// The delay before retrying after the given (1-based) failed attempt.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .initial_backoff
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+        if self.jitter {
+            delay / 2 + delay.mul_f64(random_fraction() / 2.0)
+        } else {
+            delay
+        }
+    }
+}
+
+pub(crate) fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+}
+
+pub(crate) fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout()
+}
+
+/// Reads a `Retry-After` header given in seconds or as an HTTP date.
+fn retry_after(response: &Response, now: DateTime<Utc>) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?;
+    parse_retry_after(value.to_str().ok()?, now)
+}
+
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    // A date in the past means right away.
+    Some((at.with_timezone(&Utc) - now).to_std().unwrap_or_default())
+}
+
+/// A number in `[0, 1)`. Good enough for spreading out retries, nothing more.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+    }
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::default()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(350))
+            .jitter(false);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(350));
+        assert_eq!(policy.backoff(40), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn jitter_stays_within_half_of_the_delay() {
+        let policy = RetryPolicy::default().initial_backoff(Duration::from_millis(1000));
+
+        for _ in 0..100 {
+            let delay = policy.backoff(1);
+            assert!(delay >= Duration::from_millis(500));
+            assert!(delay <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn posts_and_puts_are_opt_in() {
+        assert!(RetryPolicy::default().applies_to(&Method::GET));
+        assert!(RetryPolicy::default().applies_to(&Method::DELETE));
+        assert!(!RetryPolicy::default().applies_to(&Method::POST));
+        assert!(!RetryPolicy::default().applies_to(&Method::PUT));
+        assert!(RetryPolicy::default()
+            .retry_posts(true)
+            .applies_to(&Method::POST));
+        assert!(RetryPolicy::default()
+            .retry_puts(true)
+            .applies_to(&Method::PUT));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
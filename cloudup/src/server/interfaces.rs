@@ -0,0 +1,195 @@
+//! Network interfaces of existing servers. The server must be stopped for
+//! any changes.
+//!
+//! ```no_run
+//! # async fn example(ctx: cloudup::Context, uuid: String) -> Result<(), cloudup::Error> {
+//! use cloudup::server::{self, IpAddrTypePair, IpFamily};
+//!
+//! let added = server::interfaces::add(&ctx, uuid.clone(), &IpAddrTypePair::public(IpFamily::IPv6)).await?;
+//! server::interfaces::delete(&ctx, uuid, added.index).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use super::{IpAddrTypePair, IpAddressThing, NetworkInterface, ServerNetworkingField};
+use crate::request;
+use crate::wire;
+use crate::{Context, Error};
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct NetworkingResponse {
+    networking: ServerNetworkingField,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct InterfaceResponse {
+    interface: NetworkInterface,
+}
+
+#[derive(Serialize, Debug)]
+struct InterfacePayload<'a, T> {
+    interface: &'a T,
+}
+
+/// The parts of an interface that can be changed in place.
+#[derive(Serialize, Debug)]
+struct InterfaceChanges<'a> {
+    #[serde(
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    bootable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<u32>,
+    ip_addresses: &'a IpAddressThing,
+    #[serde(
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    source_ip_filtering: Option<bool>,
+}
+
+impl<'a> From<&'a IpAddrTypePair> for InterfaceChanges<'a> {
+    fn from(interface: &'a IpAddrTypePair) -> InterfaceChanges<'a> {
+        InterfaceChanges {
+            bootable: interface.bootable,
+            index: interface.index,
+            ip_addresses: &interface.ip_addresses,
+            source_ip_filtering: interface.source_ip_filtering,
+        }
+    }
+}
+
+/// GET /1.3/server/{uuid}/networking -> Lists the interfaces of a server.
+pub async fn list(ctx: &Context, server_uuid: String) -> Result<Vec<NetworkInterface>, Error> {
+    let response: NetworkingResponse =
+        request::get(ctx, &format!("/server/{}/networking", server_uuid)).await?;
+    Ok(response.networking.interfaces.interface)
+}
+
+/// GET /1.3/server/{uuid}/networking/interface/{index}
+pub async fn get(
+    ctx: &Context,
+    server_uuid: String,
+    index: u32,
+) -> Result<NetworkInterface, Error> {
+    let response: InterfaceResponse = request::get(
+        ctx,
+        &format!("/server/{}/networking/interface/{}", server_uuid, index),
+    )
+    .await?;
+    Ok(response.interface)
+}
+
+/// POST /1.3/server/{uuid}/networking/interface -> Adds a public, utility or
+/// private interface.
+pub async fn add(
+    ctx: &Context,
+    server_uuid: String,
+    interface: &IpAddrTypePair,
+) -> Result<NetworkInterface, Error> {
+    let response: InterfaceResponse = request::post(
+        ctx,
+        &format!("/server/{}/networking/interface", server_uuid),
+        &InterfacePayload { interface },
+    )
+    .await?;
+    Ok(response.interface)
+}
+
+/// PUT /1.3/server/{uuid}/networking/interface/{index} -> Changes the
+/// addresses of the interface at `index`, and its index, bootability and
+/// source IP filtering where `interface` sets them.
+///
+/// The type, network and MAC address of an interface can't be changed, so
+/// those of `interface` are ignored. To move a server to another network,
+/// delete the interface and add a new one.
+pub async fn modify(
+    ctx: &Context,
+    server_uuid: String,
+    index: u32,
+    interface: &IpAddrTypePair,
+) -> Result<NetworkInterface, Error> {
+    let response: InterfaceResponse = request::put(
+        ctx,
+        &format!("/server/{}/networking/interface/{}", server_uuid, index),
+        &InterfacePayload {
+            interface: &InterfaceChanges::from(interface),
+        },
+    )
+    .await?;
+    Ok(response.interface)
+}
+
+/// DELETE /1.3/server/{uuid}/networking/interface/{index}
+pub async fn delete(ctx: &Context, server_uuid: String, index: u32) -> Result<(), Error> {
+    request::delete(
+        ctx,
+        &format!("/server/{}/networking/interface/{}", server_uuid, index),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::server::{IpAddressSpecs, IpFamily};
+    use serde_json::json;
+
+    #[test]
+    fn interface_wire_format() {
+        let interface = IpAddrTypePair::private(
+            String::from("03b5b0a0-ad4c-4817-9632-dafdb3ace5d9"),
+            IpAddressSpecs::new(IpFamily::IPv4).address(String::from("172.16.0.5")),
+        )
+        .index(4)
+        .source_ip_filtering(false)
+        .bootable(true);
+
+        assert_eq!(
+            serde_json::to_value(InterfacePayload {
+                interface: &interface
+            })
+            .unwrap(),
+            json!({
+                "interface": {
+                    "bootable": "yes",
+                    "index": 4,
+                    "ip_addresses": {
+                        "ip_address": [{ "family": "IPv4", "address": "172.16.0.5" }]
+                    },
+                    "type": "private",
+                    "network": "03b5b0a0-ad4c-4817-9632-dafdb3ace5d9",
+                    "source_ip_filtering": "no"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn modify_leaves_out_type_and_network() {
+        let interface = IpAddrTypePair::private(
+            String::from("03b5b0a0-ad4c-4817-9632-dafdb3ace5d9"),
+            IpAddressSpecs::new(IpFamily::IPv4),
+        )
+        .source_ip_filtering(false);
+
+        assert_eq!(
+            serde_json::to_value(InterfacePayload {
+                interface: &InterfaceChanges::from(&interface)
+            })
+            .unwrap(),
+            json!({
+                "interface": {
+                    "ip_addresses": { "ip_address": [{ "family": "IPv4" }] },
+                    "source_ip_filtering": "no"
+                }
+            })
+        );
+    }
+}
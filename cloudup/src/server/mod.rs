@@ -1,158 +1,573 @@
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
 
-use crate::Context;
+use crate::request;
+use crate::storage::{self, StorageDetails, StorageState, StorageTier};
+use crate::wait::{self, WaitOptions};
+use crate::wire;
+use crate::{Context, Error};
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct TagField {
-    tag: Vec<String>,
+pub mod interfaces;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerState {
+    Started,
+    Stopped,
+    /// The server is being created, changed or moved and can't be touched.
+    Maintenance,
+    Error,
+    /// A state this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct TagField {
+    pub tag: Vec<String>,
+}
+
+/// Who can reach an IP address or use a storage.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessType {
+    Public,
+    Private,
+    /// UpCloud's utility network, shared by the account's servers in a zone.
+    Utility,
+    /// An access type this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+/// How many backups UpCloud keeps when simple backups are on.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupPlan {
+    /// A daily backup kept for a week.
+    Dailies,
+    /// Dailies plus weekly backups kept for a month.
+    Weeklies,
+    /// Weeklies plus monthly backups kept for a year.
+    Monthlies,
+    /// A plan this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+impl BackupPlan {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupPlan::Dailies => "dailies",
+            BackupPlan::Weeklies => "weeklies",
+            BackupPlan::Monthlies => "monthlies",
+            BackupPlan::Unknown => "unknown",
+        }
+    }
+}
+
+/// Automatic backups of every storage of a server.
+///
+/// Sent over the wire as `"no"` or as the time and plan, e.g. `"0430,dailies"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimpleBackup {
+    Off,
+    On {
+        /// Time of day in UTC as `HHMM`.
+        time: String,
+        plan: BackupPlan,
+    },
+}
+
+impl fmt::Display for SimpleBackup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimpleBackup::Off => f.write_str("no"),
+            SimpleBackup::On { time, plan } => write!(f, "{},{}", time, plan.as_str()),
+        }
+    }
+}
+
+impl Serialize for SimpleBackup {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SimpleBackup {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<SimpleBackup, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        if value == "no" {
+            return Ok(SimpleBackup::Off);
+        }
+        let (time, plan) = value
+            .split_once(',')
+            .ok_or_else(|| de::Error::custom(format!("invalid simple backup \"{}\"", value)))?;
+        let plan = match plan {
+            "dailies" => BackupPlan::Dailies,
+            "weeklies" => BackupPlan::Weeklies,
+            "monthlies" => BackupPlan::Monthlies,
+            _ => BackupPlan::Unknown,
+        };
+        Ok(SimpleBackup::On {
+            time: String::from(time),
+            plan,
+        })
+    }
+}
+
+/// A device a server can boot from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootDevice {
+    Disk,
+    Cdrom,
+    Network,
+    /// A device this library doesn't know about.
+    Unknown,
+}
+
+impl BootDevice {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BootDevice::Disk => "disk",
+            BootDevice::Cdrom => "cdrom",
+            BootDevice::Network => "network",
+            BootDevice::Unknown => "unknown",
+        }
+    }
+}
+
+/// The devices a server tries to boot from, in order.
+///
+/// Sent over the wire comma separated, e.g. `"cdrom,disk"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootOrder(pub Vec<BootDevice>);
+
+impl BootOrder {
+    /// Boots from a CD-ROM if one is loaded, and from the disk otherwise.
+    pub fn cdrom_first() -> BootOrder {
+        BootOrder(vec![BootDevice::Cdrom, BootDevice::Disk])
+    }
+
+    pub fn disk_only() -> BootOrder {
+        BootOrder(vec![BootDevice::Disk])
+    }
+}
+
+impl fmt::Display for BootOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let devices: Vec<&str> = self.0.iter().map(BootDevice::as_str).collect();
+        f.write_str(&devices.join(","))
+    }
+}
+
+impl Serialize for BootOrder {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for BootOrder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<BootOrder, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        let devices = value
+            .split(',')
+            .map(|device| match device {
+                "disk" => BootDevice::Disk,
+                "cdrom" => BootDevice::Cdrom,
+                "network" => BootDevice::Network,
+                _ => BootDevice::Unknown,
+            })
+            .collect();
+        Ok(BootOrder(devices))
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct ServerBasicInfo {
-    core_number: String,
-    created: u64,
-    host: u64,
-    hostname: String,
-    license: Decimal,
-    memory_amount: String,
-    plan: String,
-    plan_ipv4_bytes: String,
-    plan_ipv6_bytes: String,
-    simple_backup: String,
-    state: String,
-    tags: TagField,
-    title: String,
-    uuid: String,
-    zone: String,
+    #[serde(with = "wire::number_string")]
+    pub core_number: u32,
+    #[serde(with = "wire::timestamp")]
+    pub created: DateTime<Utc>,
+    pub host: u64,
+    pub hostname: String,
+    pub license: Decimal,
+    /// Memory in megabytes.
+    #[serde(with = "wire::number_string")]
+    pub memory_amount: u64,
+    pub plan: String,
+    /// Public IPv4 traffic used this month, in bytes.
+    #[serde(with = "wire::number_string")]
+    pub plan_ipv4_bytes: u64,
+    #[serde(with = "wire::number_string")]
+    pub plan_ipv6_bytes: u64,
+    pub simple_backup: SimpleBackup,
+    pub state: ServerState,
+    pub tags: TagField,
+    pub title: String,
+    pub uuid: String,
+    pub zone: String,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct GetServerResponseServersField {
     server: Vec<ServerBasicInfo>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct GetServerResponse {
     servers: GetServerResponseServersField,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct IpAddressInfo {
-    pub access: String,
+    pub access: AccessType,
     pub address: String,
-    family: String,
+    pub family: IpFamily,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub floating: Option<bool>,
+    /// MAC address of the interface the address is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub part_of_plan: Option<bool>,
+    /// Reverse DNS name of a public address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ptr_record: Option<String>,
+    /// UUID of the server the address is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct IpAddressesField {
     pub ip_address: Vec<IpAddressInfo>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateServerResponseField {
-    // boot_order: String,
-    // core_number: String,
-    // created: u64,
-    // firewall: String,
-    // hostname: String,
+/// A storage device as attached to a server.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct ServerStorageDevice {
+    /// Bus and position of the device, e.g. `virtio:0`.
+    pub address: String,
+    pub storage: String,
+    pub storage_size: usize,
+    pub storage_title: String,
+    #[serde(default)]
+    pub storage_tier: Option<StorageTier>,
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    pub storage_type: String,
+    /// Whether the server boots from this device.
+    #[serde(default, with = "wire::one_zero")]
+    pub boot_disk: bool,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub part_of_plan: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct ServerStorageDevicesField {
+    pub storage_device: Vec<ServerStorageDevice>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct InterfaceIpAddress {
+    pub address: String,
+    pub family: IpFamily,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub floating: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct InterfaceIpAddressesField {
+    pub ip_address: Vec<InterfaceIpAddress>,
+}
+
+/// A network interface of a server, as in the server details and
+/// `server::interfaces`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct NetworkInterface {
+    /// Position of the interface, from 1 up.
+    pub index: u32,
+    pub ip_addresses: InterfaceIpAddressesField,
+    pub mac: String,
+    /// UUID of the network the interface is in.
+    pub network: String,
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    pub interface_type: InterfaceType,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub source_ip_filtering: Option<bool>,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bootable: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct NetworkInterfacesField {
+    pub interface: Vec<NetworkInterface>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct ServerNetworkingField {
+    pub interfaces: NetworkInterfacesField,
+}
+
+/// Everything UpCloud knows about a single server.
+///
+/// `password` and `username` are only present in the response to creating
+/// a server, and `progress` only while the server is being created.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct ServerDetails {
+    pub boot_order: BootOrder,
+    #[serde(with = "wire::number_string")]
+    pub core_number: u32,
+    #[serde(
+        default,
+        with = "wire::optional_timestamp",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(with = "wire::on_off")]
+    pub firewall: bool,
+    #[serde(default)]
+    pub host: Option<u64>,
+    pub hostname: String,
     pub ip_addresses: IpAddressesField,
-    // license: u64,
-    // memory_amount: String,
-    // metadata: String,
-    // networking: serde_json::Value,
-    // nic_model: String,
-    // password: String,
-    // plan: String,
-    // plan_ipv4_bytes: String,
-    // plan_ipv6_bytes: String,
-    // progress: String,
-    // remote_access_enabled: String,
-    // remote_access_password: String,
-    // remote_access_type: String,
-    // simple_backup: String,
-    // state: String,
-    // storage_devices: serde_json::Value,
-    // tags: TagField,
-    // timezone: String,
-    // title: String,
-    // username: String,
-    // uuid: String,
-    // video_model: String,
-    // zone: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateServerResponse {
-    pub server: CreateServerResponseField,
-}
-
-pub async fn get_servers(ctx: &Context) -> Result<Vec<ServerBasicInfo>, reqwest::Error> {
-    let url = format!("{}/1.3/server", ctx.uc_baseurl);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .get(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let response: Result<GetServerResponse, _> = match request {
-        Ok(response) => response.json::<GetServerResponse>().await,
-        Err(error) => {
-            panic!("{}", error);
+    pub license: Decimal,
+    /// Memory in megabytes.
+    #[serde(with = "wire::number_string")]
+    pub memory_amount: u64,
+    #[serde(with = "wire::yes_no")]
+    pub metadata: bool,
+    pub networking: ServerNetworkingField,
+    pub nic_model: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub plan: String,
+    #[serde(with = "wire::number_string")]
+    pub plan_ipv4_bytes: u64,
+    #[serde(with = "wire::number_string")]
+    pub plan_ipv6_bytes: u64,
+    #[serde(default)]
+    pub progress: Option<String>,
+    #[serde(with = "wire::yes_no")]
+    pub remote_access_enabled: bool,
+    #[serde(default)]
+    pub remote_access_password: Option<String>,
+    #[serde(default)]
+    pub remote_access_type: Option<String>,
+    pub simple_backup: SimpleBackup,
+    pub state: ServerState,
+    pub storage_devices: ServerStorageDevicesField,
+    pub tags: TagField,
+    pub timezone: String,
+    pub title: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    pub uuid: String,
+    pub video_model: String,
+    pub zone: String,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ServerDetails {
+    /// The network interfaces of the server, in index order.
+    pub fn interfaces(&self) -> &[NetworkInterface] {
+        &self.networking.interfaces.interface
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub(crate) struct ServerDetailsResponse {
+    pub(crate) server: ServerDetails,
+}
+
+/// GET /1.3/server -> Lists all the servers of the account.
+pub async fn get_servers(ctx: &Context) -> Result<Vec<ServerBasicInfo>, Error> {
+    let response: GetServerResponse = request::get(ctx, "/server").await?;
+    Ok(response.servers.server)
+}
+
+/// GET /1.3/server/{uuid} -> Returns the details of a single server.
+pub async fn get_server(ctx: &Context, uuid: String) -> Result<ServerDetails, Error> {
+    let response: ServerDetailsResponse = request::get(ctx, &format!("/server/{}", uuid)).await?;
+    Ok(response.server)
+}
+
+/// Polls the server every five seconds until it reaches `state`.
+///
+/// ```no_run
+/// # async fn example(ctx: cloudup::Context, uuid: String) -> Result<(), cloudup::Error> {
+/// use std::time::Duration;
+/// use cloudup::server::{self, ServerState};
+///
+/// server::stop(&ctx, uuid.clone(), &Default::default()).await?;
+/// server::wait_for_state(&ctx, uuid.clone(), ServerState::Stopped, Duration::from_secs(120)).await?;
+/// server::delete_along_storage(&ctx, uuid).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn wait_for_state(
+    ctx: &Context,
+    uuid: String,
+    state: ServerState,
+    timeout: Duration,
+) -> Result<ServerDetails, Error> {
+    wait_for_state_with(
+        ctx,
+        uuid,
+        state,
+        &WaitOptions::new(timeout),
+        std::future::pending(),
+    )
+    .await
+}
+
+/// Like `wait_for_state`, but with a custom poll interval and a `cancel`
+/// future that stops the wait with `Error::Cancelled` when it completes.
+pub async fn wait_for_state_with<C: Future<Output = ()>>(
+    ctx: &Context,
+    uuid: String,
+    state: ServerState,
+    options: &WaitOptions,
+    cancel: C,
+) -> Result<ServerDetails, Error> {
+    wait::until(
+        options,
+        cancel,
+        || get_server(ctx, uuid.clone()),
+        |server| server.state == state,
+    )
+    .await
+}
+
+/// DELETE /1.3/server/{uuid} -> Deletes a stopped server, leaving its storages intact.
+pub async fn delete(ctx: &Context, uuid: String) -> Result<(), Error> {
+    request::delete(ctx, &format!("/server/{}", uuid)).await
+}
+
+/// DELETE /1.3/server/{uuid}?storages=1 -> Deletes a stopped server and its storages.
+pub async fn delete_along_storage(ctx: &Context, uuid: String) -> Result<(), Error> {
+    request::delete(ctx, &format!("/server/{}?storages=1", uuid)).await
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StopType {
+    /// Asks the operating system to shut down, like pressing the power button.
+    Soft,
+    /// Cuts the power.
+    Hard,
+}
+
+/// How to stop a server.
+#[derive(Debug, Clone)]
+pub struct StopOptions {
+    pub stop_type: StopType,
+    /// How long a soft stop may take before UpCloud gives up on it.
+    pub timeout: Duration,
+}
+
+impl Default for StopOptions {
+    fn default() -> StopOptions {
+        StopOptions::soft(Duration::from_secs(30))
+    }
+}
+
+impl StopOptions {
+    pub fn soft(timeout: Duration) -> StopOptions {
+        StopOptions {
+            stop_type: StopType::Soft,
+            timeout,
         }
-    };
+    }
 
-    return match response {
-        Ok(payload) => Ok(payload.servers.server),
-        Err(err) => panic!("{}", err),
-    };
+    pub fn hard() -> StopOptions {
+        StopOptions {
+            stop_type: StopType::Hard,
+            timeout: Duration::from_secs(0),
+        }
+    }
 }
 
-pub async fn delete(ctx: &Context, uuid: String) -> Result<(), reqwest::Error> {
-    let url = format!("{}/1.3/server/{}", ctx.uc_baseurl, uuid);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .delete(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let result = match request {
-        Ok(response) => match response.status() {
-            reqwest::StatusCode::NO_CONTENT => Ok(()),
-            _ => panic!(),
-        },
-        Err(err) => Err(err),
-    };
-    return result;
-}
-
-pub async fn delete_along_storage(ctx: &Context, uuid: String) -> Result<(), reqwest::Error> {
-    let url = format!("{}/1.3/server/{}/?storages=1", ctx.uc_baseurl, uuid);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .delete(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let result = match request {
-        Ok(response) => match response.status() {
-            reqwest::StatusCode::NO_CONTENT => Ok(()),
-            _ => panic!(),
-        },
-        Err(err) => Err(err),
-    };
-    return result;
+/// What a restart does when a soft stop runs out of time.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeoutAction {
+    /// Stops the server hard and starts it again.
+    Destroy,
+    /// Leaves the server running.
+    Ignore,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(deny_unknown_fields)]
 struct StopServerField {
-    stop_type: String,
+    stop_type: StopType,
     timeout: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout_action: Option<TimeoutAction>,
+}
+
+impl StopServerField {
+    fn new(options: &StopOptions, timeout_action: Option<TimeoutAction>) -> StopServerField {
+        StopServerField {
+            stop_type: options.stop_type,
+            timeout: options.timeout.as_secs().to_string(),
+            timeout_action,
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -161,204 +576,959 @@ struct StopServerPayload {
     stop_server: StopServerField,
 }
 
-pub async fn stop(ctx: &Context, uuid: String) -> Result<(), reqwest::Error> {
-    let url = format!("{}/1.3/server/{}/stop", ctx.uc_baseurl, uuid);
-    let client = reqwest::Client::new();
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RestartServerPayload {
+    restart_server: StopServerField,
+}
+
+/// Where a server may be started.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StartOptions {
+    /// Start on this host. Only available on private clouds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<u64>,
+    /// Start on any host but this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avoid_host: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+struct StartServerPayload<'a> {
+    server: &'a StartOptions,
+}
+
+/// POST /1.3/server/{uuid}/stop -> Stops a started server.
+pub async fn stop(
+    ctx: &Context,
+    uuid: String,
+    options: &StopOptions,
+) -> Result<ServerDetails, Error> {
     let payload_body = StopServerPayload {
-        stop_server: StopServerField {
-            stop_type: String::from("soft"),
-            timeout: String::from("30"),
-        },
+        stop_server: StopServerField::new(options, None),
+    };
+    let response: ServerDetailsResponse =
+        request::post(ctx, &format!("/server/{}/stop", uuid), &payload_body).await?;
+    Ok(response.server)
+}
+
+/// POST /1.3/server/{uuid}/start -> Starts a stopped server.
+pub async fn start(
+    ctx: &Context,
+    uuid: String,
+    options: &StartOptions,
+) -> Result<ServerDetails, Error> {
+    let payload_body = StartServerPayload { server: options };
+    let response: ServerDetailsResponse =
+        request::post(ctx, &format!("/server/{}/start", uuid), &payload_body).await?;
+    Ok(response.server)
+}
+
+/// POST /1.3/server/{uuid}/restart -> Stops a started server and starts it again.
+pub async fn restart(
+    ctx: &Context,
+    uuid: String,
+    options: &StopOptions,
+    timeout_action: TimeoutAction,
+) -> Result<ServerDetails, Error> {
+    let payload_body = RestartServerPayload {
+        restart_server: StopServerField::new(options, Some(timeout_action)),
+    };
+    let response: ServerDetailsResponse =
+        request::post(ctx, &format!("/server/{}/restart", uuid), &payload_body).await?;
+    Ok(response.server)
+}
+
+/// POST /1.3/server/{uuid}/cancel -> Cancels a running stop or restart.
+pub async fn cancel_operation(ctx: &Context, uuid: String) -> Result<ServerDetails, Error> {
+    let response: ServerDetailsResponse =
+        request::post_empty(ctx, &format!("/server/{}/cancel", uuid)).await?;
+    Ok(response.server)
+}
+
+/// How `server::bake_template` goes about it.
+#[derive(Debug, Clone)]
+pub struct BakeOptions {
+    pub stop: StopOptions,
+    /// For each wait: for the server to stop and for the storages to come
+    /// back online.
+    pub wait: WaitOptions,
+    /// Start the server again afterwards, if it was running to begin with.
+    pub restart: bool,
+}
+
+impl BakeOptions {
+    pub fn new(wait: WaitOptions) -> BakeOptions {
+        BakeOptions {
+            stop: StopOptions::default(),
+            wait,
+            restart: false,
+        }
+    }
+
+    pub fn stop(mut self, stop: StopOptions) -> BakeOptions {
+        self.stop = stop;
+        self
+    }
+
+    pub fn restart(mut self, restart: bool) -> BakeOptions {
+        self.restart = restart;
+        self
+    }
+}
+
+/// Makes a template out of the boot disk of a server, stopping the server
+/// for the duration, and returns the template once it's online.
+///
+/// With `restart`, the server is started again even if making the template
+/// fails, and the error of the template is returned.
+///
+/// ```no_run
+/// # async fn example(ctx: cloudup::Context, uuid: String) -> Result<(), cloudup::Error> {
+/// use std::time::Duration;
+/// use cloudup::server::{self, BakeOptions};
+///
+/// let options = BakeOptions::new(cloudup::WaitOptions::new(Duration::from_secs(600))).restart(true);
+/// let template = server::bake_template(&ctx, uuid, String::from("Minecraft 1.17"), &options).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn bake_template(
+    ctx: &Context,
+    uuid: String,
+    title: String,
+    options: &BakeOptions,
+) -> Result<StorageDetails, Error> {
+    // Let whatever the server is in the middle of finish first.
+    let server = wait::until(
+        &options.wait,
+        std::future::pending(),
+        || get_server(ctx, uuid.clone()),
+        |server| server.state != ServerState::Maintenance,
+    )
+    .await?;
+    let was_started = server.state == ServerState::Started;
+    if was_started {
+        stop(ctx, uuid.clone(), &options.stop).await?;
+    }
+    let result = templatize_boot_disk(ctx, server, was_started, title, options).await;
+
+    if options.restart && was_started {
+        if result.is_err() {
+            // The server can't start while its disks are still busy.
+            wait_for_disks(ctx, &uuid, options).await;
+        }
+        let started = start(ctx, uuid, &StartOptions::default()).await;
+        // After a failed bake, its error is the one worth reporting.
+        let template = result?;
+        started?;
+        return storage::get(ctx, template).await;
+    }
+    storage::get(ctx, result?).await
+}
+
+/// Waits for the server to stop, if it's stopping, and makes a template of
+/// its boot disk. Returns the UUID of the template once it's online.
+async fn templatize_boot_disk(
+    ctx: &Context,
+    mut server: ServerDetails,
+    stopping: bool,
+    title: String,
+    options: &BakeOptions,
+) -> Result<String, Error> {
+    if stopping {
+        server = wait_for_state_with(
+            ctx,
+            server.uuid.clone(),
+            ServerState::Stopped,
+            &options.wait,
+            std::future::pending(),
+        )
+        .await?;
+    }
+
+    let devices = &server.storage_devices.storage_device;
+    let disk = devices
+        .iter()
+        .find(|device| device.boot_disk)
+        .or_else(|| devices.iter().find(|device| device.storage_type == "disk"))
+        .map(|device| device.storage.clone());
+    let disk = disk.ok_or_else(|| Error::NoDisk(server.uuid.clone()))?;
+
+    let template = storage::templatize(ctx, disk.clone(), title).await?;
+    for storage in [disk, template.uuid.clone()] {
+        storage::wait_for_state_with(
+            ctx,
+            storage,
+            StorageState::Online,
+            &options.wait,
+            std::future::pending(),
+        )
+        .await?;
+    }
+    Ok(template.uuid)
+}
+
+/// Waits for the disks of the server to come online, as far as it can tell.
+async fn wait_for_disks(ctx: &Context, uuid: &str, options: &BakeOptions) {
+    let server = match get_server(ctx, String::from(uuid)).await {
+        Ok(server) => server,
+        Err(_) => return,
     };
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .post(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .json(&payload_body)
-        .send()
-        .await;
-    let result = match request {
-        Ok(response) => match response.status() {
-            reqwest::StatusCode::OK => Ok(()),
-            _ => panic!(),
+    for device in server.storage_devices.storage_device {
+        if device.storage_type == "disk" {
+            storage::wait_for_state_with(
+                ctx,
+                device.storage,
+                StorageState::Online,
+                &options.wait,
+                std::future::pending(),
+            )
+            .await
+            .ok();
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct BootOrderField<'a> {
+    boot_order: &'a BootOrder,
+}
+
+#[derive(Serialize, Debug)]
+struct ModifyServerPayload<'a> {
+    server: BootOrderField<'a>,
+}
+
+/// PUT /1.3/server/{uuid} -> Changes where the server boots from. Takes
+/// effect on the next start.
+pub async fn set_boot_order(
+    ctx: &Context,
+    uuid: String,
+    boot_order: &BootOrder,
+) -> Result<ServerDetails, Error> {
+    let body = ModifyServerPayload {
+        server: BootOrderField { boot_order },
+    };
+    let response: ServerDetailsResponse =
+        request::put(ctx, &format!("/server/{}", uuid), &body).await?;
+    Ok(response.server)
+}
+
+#[derive(Serialize, Debug)]
+struct CdromField {
+    storage: String,
+}
+
+#[derive(Serialize, Debug)]
+struct CdromPayload {
+    storage_device: CdromField,
+}
+
+/// POST /1.3/server/{uuid}/cdrom/load -> Puts a CD-ROM storage, such as a
+/// rescue ISO, into the server's CD-ROM drive.
+///
+/// To boot from it, set a boot order with the CD-ROM first and restart.
+pub async fn load_cdrom(
+    ctx: &Context,
+    uuid: String,
+    storage_uuid: String,
+) -> Result<ServerDetails, Error> {
+    let body = CdromPayload {
+        storage_device: CdromField {
+            storage: storage_uuid,
         },
-        Err(err) => Err(err),
     };
-    return result;
+    let response: ServerDetailsResponse =
+        request::post(ctx, &format!("/server/{}/cdrom/load", uuid), &body).await?;
+    Ok(response.server)
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// POST /1.3/server/{uuid}/cdrom/eject -> Empties the server's CD-ROM drive.
+pub async fn eject_cdrom(ctx: &Context, uuid: String) -> Result<ServerDetails, Error> {
+    let response: ServerDetailsResponse =
+        request::post_empty(ctx, &format!("/server/{}/cdrom/eject", uuid)).await?;
+    Ok(response.server)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageAction {
+    /// Creates a new empty storage.
+    Create,
+    /// Clones an existing storage or template.
+    Clone,
+    /// Attaches an existing storage as is.
+    Attach,
+}
+
+/// A storage device to create, clone or attach along with a new server.
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
-struct StartServerStorageDevice {
-    action: String,
-    storage: String,
-    title: String,
-    size: usize,
-    tier: String,
+pub struct StorageDevice {
+    action: StorageAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    storage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tier: Option<StorageTier>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl StorageDevice {
+    /// A new empty storage of `size` gigabytes.
+    pub fn create(size: usize) -> StorageDevice {
+        StorageDevice {
+            action: StorageAction::Create,
+            storage: None,
+            title: None,
+            size: Some(size),
+            tier: None,
+        }
+    }
+
+    /// A copy of the storage or template `uuid`, grown to `size` gigabytes.
+    pub fn clone_from(uuid: String, size: usize) -> StorageDevice {
+        StorageDevice {
+            action: StorageAction::Clone,
+            storage: Some(uuid),
+            title: None,
+            size: Some(size),
+            tier: None,
+        }
+    }
+
+    /// The existing storage `uuid`.
+    pub fn attach(uuid: String) -> StorageDevice {
+        StorageDevice {
+            action: StorageAction::Attach,
+            storage: Some(uuid),
+            title: None,
+            size: None,
+            tier: None,
+        }
+    }
+
+    pub fn title(mut self, title: String) -> StorageDevice {
+        self.title = Some(title);
+        self
+    }
+
+    /// Defaults to UpCloud's zone specific default tier when not given.
+    pub fn tier(mut self, tier: StorageTier) -> StorageDevice {
+        self.tier = Some(tier);
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 struct StartServerStorageDevicesField {
-    storage_device: Vec<StartServerStorageDevice>,
+    storage_device: Vec<StorageDevice>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    IPv4,
+    IPv6,
+    /// A family this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
-struct IpAddressSpecs {
-    family: String,
+pub struct IpAddressSpecs {
+    family: IpFamily,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl IpAddressSpecs {
+    /// Any free address of the given family.
+    pub fn new(family: IpFamily) -> IpAddressSpecs {
+        IpAddressSpecs {
+            family,
+            address: None,
+        }
+    }
+
+    /// A specific address. Only private networks let you pick one.
+    pub fn address(mut self, address: String) -> IpAddressSpecs {
+        self.address = Some(address);
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 struct IpAddressThing {
     ip_address: Vec<IpAddressSpecs>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InterfaceType {
+    Public,
+    Utility,
+    Private,
+    /// An interface type this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+/// A network interface and the addresses it should get.
+///
+/// ```no_run
+/// # use cloudup::server::{IpAddrTypePair, IpAddressSpecs, IpFamily};
+/// let router = IpAddrTypePair::private(
+///     String::from("03b5b0a0-ad4c-4817-9632-dafdb3ace5d9"),
+///     IpAddressSpecs::new(IpFamily::IPv4),
+/// )
+/// .index(2)
+/// .source_ip_filtering(false);
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
-struct IpAddrTypePair {
+pub struct IpAddrTypePair {
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    bootable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<u32>,
     ip_addresses: IpAddressThing,
     #[serde(rename(serialize = "type", deserialize = "type"))]
-    ip_address_type: String,
+    ip_address_type: InterfaceType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    source_ip_filtering: Option<bool>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl IpAddrTypePair {
+    /// An interface on the public internet.
+    pub fn public(family: IpFamily) -> IpAddrTypePair {
+        IpAddrTypePair::new(InterfaceType::Public, IpAddressSpecs::new(family), None)
+    }
+
+    /// An interface on UpCloud's utility network, shared by the account's servers in a zone.
+    pub fn utility() -> IpAddrTypePair {
+        IpAddrTypePair::new(
+            InterfaceType::Utility,
+            IpAddressSpecs::new(IpFamily::IPv4),
+            None,
+        )
+    }
+
+    /// An interface on the private SDN network `network_uuid`.
+    pub fn private(network_uuid: String, address: IpAddressSpecs) -> IpAddrTypePair {
+        IpAddrTypePair::new(InterfaceType::Private, address, Some(network_uuid))
+    }
+
+    fn new(
+        interface_type: InterfaceType,
+        address: IpAddressSpecs,
+        network: Option<String>,
+    ) -> IpAddrTypePair {
+        IpAddrTypePair {
+            bootable: None,
+            index: None,
+            ip_addresses: IpAddressThing {
+                ip_address: vec![address],
+            },
+            ip_address_type: interface_type,
+            network,
+            source_ip_filtering: None,
+        }
+    }
+
+    /// Asks for one more address on the same interface.
+    pub fn ip_address(mut self, address: IpAddressSpecs) -> IpAddrTypePair {
+        self.ip_addresses.ip_address.push(address);
+        self
+    }
+
+    /// Position of the interface, from 1 up. UpCloud picks the next free one
+    /// by default.
+    pub fn index(mut self, index: u32) -> IpAddrTypePair {
+        self.index = Some(index);
+        self
+    }
+
+    /// Whether the server may only send traffic from its own addresses. On by
+    /// default; servers that route traffic need it off.
+    pub fn source_ip_filtering(mut self, source_ip_filtering: bool) -> IpAddrTypePair {
+        self.source_ip_filtering = Some(source_ip_filtering);
+        self
+    }
+
+    /// Whether the server can boot over the network through the interface.
+    pub fn bootable(mut self, bootable: bool) -> IpAddrTypePair {
+        self.bootable = Some(bootable);
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 struct StartServerInterfacesField {
     interface: Vec<IpAddrTypePair>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 struct StartServerNetworkingField {
     interfaces: StartServerInterfacesField,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 struct StartServerSshKeysField {
     ssh_key: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// The user created on a server cloned from a template.
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
-struct StartServerLoginUserField {
+pub struct LoginUser {
     username: String,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    create_password: Option<bool>,
     ssh_keys: StartServerSshKeysField,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl LoginUser {
+    pub fn new(username: String, ssh_keys: Vec<String>) -> LoginUser {
+        LoginUser {
+            username,
+            create_password: None,
+            ssh_keys: StartServerSshKeysField { ssh_key: ssh_keys },
+        }
+    }
+
+    /// Whether UpCloud should generate a password for the user as well.
+    pub fn create_password(mut self, create_password: bool) -> LoginUser {
+        self.create_password = Some(create_password);
+        self
+    }
+}
+
+/// Describes a server to create with `server::create`.
+///
+/// ```no_run
+/// # use cloudup::server::*;
+/// # use cloudup::storage::StorageTier;
+/// let request = ServerCreateRequest::new(
+///     String::from("fi-hel1"),
+///     String::from("Minecraft"),
+///     String::from("minecraft.example.com"),
+/// )
+/// .plan(String::from("2xCPU-4GB"))
+/// .storage_device(
+///     StorageDevice::clone_from(String::from("01000000-0000-4000-8000-000050010300"), 80)
+///         .tier(StorageTier::Maxiops),
+/// )
+/// .interface(IpAddrTypePair::public(IpFamily::IPv4))
+/// .interface(IpAddrTypePair::public(IpFamily::IPv6))
+/// .login_user(LoginUser::new(String::from("steve"), vec![]));
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
-struct StartServerField {
+pub struct ServerCreateRequest {
     zone: String,
     title: String,
     hostname: String,
-    plan: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plan: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    core_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory_amount: Option<String>,
     storage_devices: StartServerStorageDevicesField,
     networking: StartServerNetworkingField,
-    login_user: StartServerLoginUserField,
-    user_data: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    login_user: Option<LoginUser>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_data: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    metadata: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_on_off",
+        skip_serializing_if = "Option::is_none"
+    )]
+    firewall: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<TagField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    boot_order: Option<BootOrder>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct StartServerPayload {
-    server: StartServerField,
-}
-
-pub async fn start(ctx: &Context, uuid: String) -> Result<(), reqwest::Error> {
-    let url = format!("{}/1.3/server/{}/start", ctx.uc_baseurl, uuid);
-    let client = reqwest::Client::new();
-    let payload_body = StopServerPayload {
-        stop_server: StopServerField {
-            stop_type: String::from("soft"),
-            timeout: String::from("30"),
-        },
-    };
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .post(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .json(&payload_body)
-        .send()
-        .await;
-    let result = match request {
-        Ok(response) => match response.status() {
-            reqwest::StatusCode::OK => Ok(()),
-            _ => panic!(),
-        },
-        Err(err) => Err(err),
-    };
-    return result;
-}
-
-pub async fn create_from_template(
-    ctx: &Context,
-    template_uuid: String,
-    hostname: String,
-    title: String,
-    storage_title: String,
-    storage_size: usize,
-    plan_name: String,
-    zone: String,
-    user_data: String,
-    username: String,
-    ssh_keys: Vec<String>,
-) -> Result<CreateServerResponse, reqwest::Error> {
-    let url = format!("{}/1.3/server/", ctx.uc_baseurl);
-    let client = reqwest::Client::new();
-    let payload_body = StartServerPayload {
-        server: StartServerField {
+impl ServerCreateRequest {
+    pub fn new(zone: String, title: String, hostname: String) -> ServerCreateRequest {
+        ServerCreateRequest {
             zone,
             title,
             hostname,
-            plan: plan_name,
+            plan: None,
+            core_number: None,
+            memory_amount: None,
             storage_devices: StartServerStorageDevicesField {
-                storage_device: vec![StartServerStorageDevice {
-                    action: String::from("clone"),
-                    storage: template_uuid,
-                    title: storage_title,
-                    size: storage_size,
-                    tier: String::from("maxiops"),
-                }],
+                storage_device: vec![],
             },
             networking: StartServerNetworkingField {
-                interfaces: StartServerInterfacesField {
-                    interface: vec![
-                        IpAddrTypePair {
-                            ip_addresses: IpAddressThing {
-                                ip_address: vec![IpAddressSpecs {
-                                    family: String::from("IPv4"),
-                                }],
-                            },
-                            ip_address_type: String::from("public"),
+                interfaces: StartServerInterfacesField { interface: vec![] },
+            },
+            login_user: None,
+            user_data: None,
+            metadata: None,
+            timezone: None,
+            firewall: None,
+            tags: None,
+            boot_order: None,
+        }
+    }
+
+    /// One of the plan names returned by `plan::get_plans`.
+    pub fn plan(mut self, plan: String) -> ServerCreateRequest {
+        self.plan = Some(plan);
+        self
+    }
+
+    /// A custom configuration instead of a plan. Memory is given in megabytes.
+    pub fn custom(mut self, core_number: u32, memory_amount: u64) -> ServerCreateRequest {
+        self.plan = Some(String::from("custom"));
+        self.core_number = Some(core_number.to_string());
+        self.memory_amount = Some(memory_amount.to_string());
+        self
+    }
+
+    /// Adds a storage device. The first one is the one the server boots from.
+    pub fn storage_device(mut self, device: StorageDevice) -> ServerCreateRequest {
+        self.storage_devices.storage_device.push(device);
+        self
+    }
+
+    pub fn interface(mut self, interface: IpAddrTypePair) -> ServerCreateRequest {
+        self.networking.interfaces.interface.push(interface);
+        self
+    }
+
+    pub fn login_user(mut self, login_user: LoginUser) -> ServerCreateRequest {
+        self.login_user = Some(login_user);
+        self
+    }
+
+    /// A script or URL run on the first boot of a server cloned from a template.
+    pub fn user_data(mut self, user_data: String) -> ServerCreateRequest {
+        self.user_data = Some(user_data);
+        self
+    }
+
+    /// Enables the metadata service. Required by cloud-init based templates.
+    pub fn metadata(mut self, metadata: bool) -> ServerCreateRequest {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn timezone(mut self, timezone: String) -> ServerCreateRequest {
+        self.timezone = Some(timezone);
+        self
+    }
+
+    /// Enables UpCloud's firewall in front of the server.
+    pub fn firewall(mut self, firewall: bool) -> ServerCreateRequest {
+        self.firewall = Some(firewall);
+        self
+    }
+
+    /// Where the server boots from. UpCloud boots from the disk by default.
+    pub fn boot_order(mut self, boot_order: BootOrder) -> ServerCreateRequest {
+        self.boot_order = Some(boot_order);
+        self
+    }
+
+    pub fn tag(mut self, tag: String) -> ServerCreateRequest {
+        self.tags
+            .get_or_insert_with(|| TagField { tag: vec![] })
+            .tag
+            .push(tag);
+        self
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct CreateServerPayload<'a> {
+    server: &'a ServerCreateRequest,
+}
+
+/// POST /1.3/server -> Creates and starts a new server.
+pub async fn create(ctx: &Context, server: &ServerCreateRequest) -> Result<ServerDetails, Error> {
+    let response: ServerDetailsResponse =
+        request::post(ctx, "/server", &CreateServerPayload { server }).await?;
+    Ok(response.server)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn create_request_wire_format() {
+        let request = ServerCreateRequest::new(
+            String::from("fi-hel1"),
+            String::from("My Server"),
+            String::from("my.example.com"),
+        )
+        .plan(String::from("1xCPU-2GB"))
+        .storage_device(
+            StorageDevice::clone_from(String::from("01000000-0000-4000-8000-000030200200"), 50)
+                .title(String::from("system"))
+                .tier(StorageTier::Maxiops),
+        )
+        .storage_device(StorageDevice::create(100).tier(StorageTier::Hdd))
+        .interface(IpAddrTypePair::public(IpFamily::IPv4))
+        .interface(IpAddrTypePair::utility())
+        .login_user(LoginUser::new(
+            String::from("upclouduser"),
+            vec![String::from("ssh-rsa AAAA")],
+        ))
+        .metadata(true)
+        .firewall(false)
+        .tag(String::from("DEV"));
+
+        let expected = json!({
+            "server": {
+                "zone": "fi-hel1",
+                "title": "My Server",
+                "hostname": "my.example.com",
+                "plan": "1xCPU-2GB",
+                "storage_devices": {
+                    "storage_device": [
+                        {
+                            "action": "clone",
+                            "storage": "01000000-0000-4000-8000-000030200200",
+                            "title": "system",
+                            "size": 50,
+                            "tier": "maxiops"
                         },
-                        //   IpAddrTypePair {
-                        //     ip_addresses: IpAddressThing { ip_address: vec![ IpAddressSpecs { family: String::from("IPv4") }] },
-                        //     ip_address_type: String::from("utility")
-                        //   },
-                        //   IpAddrTypePair {
-                        //     ip_addresses: IpAddressThing { ip_address: vec![ IpAddressSpecs { family: String::from("IPv6") }] },
-                        //     ip_address_type: String::from("public")
-                        //   }
-                    ],
+                        { "action": "create", "size": 100, "tier": "hdd" }
+                    ]
                 },
-            },
-            login_user: StartServerLoginUserField {
-                username,
-                ssh_keys: StartServerSshKeysField { ssh_key: ssh_keys },
-            },
-            user_data,
-        },
-    };
+                "networking": {
+                    "interfaces": {
+                        "interface": [
+                            { "ip_addresses": { "ip_address": [{ "family": "IPv4" }] }, "type": "public" },
+                            { "ip_addresses": { "ip_address": [{ "family": "IPv4" }] }, "type": "utility" }
+                        ]
+                    }
+                },
+                "login_user": {
+                    "username": "upclouduser",
+                    "ssh_keys": { "ssh_key": ["ssh-rsa AAAA"] }
+                },
+                "metadata": "yes",
+                "firewall": "off",
+                "tags": { "tag": ["DEV"] }
+            }
+        });
 
-    let json_response = client
-        .post(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .json(&payload_body)
-        .send()
-        .await?
-        .json()
-        .await?;
+        assert_eq!(
+            serde_json::to_value(CreateServerPayload { server: &request }).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn server_details() {
+        let body = json!({
+            "server": {
+                "boot_order": "disk",
+                "core_number": "1",
+                "created": 1598526319,
+                "firewall": "off",
+                "host": 7653311107u64,
+                "hostname": "server1.example.com",
+                "ip_addresses": {
+                    "ip_address": [
+                        { "access": "private", "address": "10.6.3.95", "family": "IPv4" },
+                        { "access": "public", "address": "94.237.0.207", "family": "IPv4", "part_of_plan": "yes" }
+                    ]
+                },
+                "license": 0,
+                "memory_amount": "1024",
+                "metadata": "yes",
+                "networking": {
+                    "interfaces": {
+                        "interface": [
+                            {
+                                "index": 1,
+                                "ip_addresses": {
+                                    "ip_address": [{ "address": "94.237.0.207", "family": "IPv4", "floating": "no" }]
+                                },
+                                "mac": "de:ff:ff:ff:66:89",
+                                "network": "037fcf2a-6745-45dd-867e-f9479ea8c044",
+                                "source_ip_filtering": "yes",
+                                "type": "public",
+                                "bootable": "no"
+                            }
+                        ]
+                    }
+                },
+                "nic_model": "virtio",
+                "plan": "1xCPU-1GB",
+                "plan_ipv4_bytes": "3565675",
+                "plan_ipv6_bytes": "4488",
+                "remote_access_enabled": "no",
+                "remote_access_password": "aabbccdd",
+                "remote_access_type": "vnc",
+                "simple_backup": "0100,dailies",
+                "state": "started",
+                "storage_devices": {
+                    "storage_device": [
+                        {
+                            "address": "virtio:0",
+                            "part_of_plan": "yes",
+                            "storage": "012580a1-32a1-466e-a323-689ca16f2d43",
+                            "storage_size": 25,
+                            "storage_tier": "maxiops",
+                            "storage_title": "Storage for server1.example.com",
+                            "type": "disk",
+                            "boot_disk": "0"
+                        }
+                    ]
+                },
+                "tags": { "tag": ["PROD"] },
+                "timezone": "UTC",
+                "title": "server1.example.com",
+                "uuid": "0077fa3d-32db-4b09-9f5f-30d9e9afb565",
+                "video_model": "vga",
+                "zone": "fi-hel1"
+            }
+        });
+
+        let details = serde_json::from_value::<ServerDetailsResponse>(body)
+            .unwrap()
+            .server;
+
+        assert_eq!(details.state, ServerState::Started);
+        assert_eq!(details.storage_devices.storage_device[0].storage_size, 25);
+        assert_eq!(
+            details.networking.interfaces.interface[0].interface_type,
+            InterfaceType::Public
+        );
+        assert_eq!(details.progress, None);
+        assert_eq!(details.core_number, 1);
+        assert_eq!(details.plan_ipv4_bytes, 3565675);
+        assert!(!details.firewall);
+        assert!(details.metadata);
+        assert_eq!(details.created.unwrap().timestamp(), 1598526319);
+        assert_eq!(
+            details.ip_addresses.ip_address[0].access,
+            AccessType::Private
+        );
+        assert_eq!(
+            details.networking.interfaces.interface[0]
+                .ip_addresses
+                .ip_address[0]
+                .floating,
+            Some(false)
+        );
+        assert_eq!(
+            details.simple_backup,
+            SimpleBackup::On {
+                time: String::from("0100"),
+                plan: BackupPlan::Dailies
+            }
+        );
+    }
+
+    #[test]
+    fn simple_backup_wire_format() {
+        let off: SimpleBackup = serde_json::from_value(json!("no")).unwrap();
+        let on = SimpleBackup::On {
+            time: String::from("0430"),
+            plan: BackupPlan::Monthlies,
+        };
+
+        assert_eq!(off, SimpleBackup::Off);
+        assert_eq!(serde_json::to_value(&on).unwrap(), json!("0430,monthlies"));
+        assert_eq!(
+            serde_json::from_value::<SimpleBackup>(json!("0430,hourlies")).unwrap(),
+            SimpleBackup::On {
+                time: String::from("0430"),
+                plan: BackupPlan::Unknown,
+            }
+        );
+        assert!(serde_json::from_value::<SimpleBackup>(json!("0430")).is_err());
+    }
+
+    #[test]
+    fn boot_order_wire_format() {
+        let order: BootOrder = serde_json::from_value(json!("cdrom,disk")).unwrap();
+
+        assert_eq!(order, BootOrder::cdrom_first());
+        assert_eq!(
+            serde_json::to_value(BootOrder(vec![BootDevice::Network, BootDevice::Disk])).unwrap(),
+            json!("network,disk")
+        );
+        assert_eq!(
+            serde_json::from_value::<BootOrder>(json!("floppy,disk")).unwrap(),
+            BootOrder(vec![BootDevice::Unknown, BootDevice::Disk])
+        );
+    }
+
+    #[test]
+    fn stop_and_restart_wire_format() {
+        let stop = StopServerPayload {
+            stop_server: StopServerField::new(&StopOptions::soft(Duration::from_secs(60)), None),
+        };
+        let restart = RestartServerPayload {
+            restart_server: StopServerField::new(
+                &StopOptions::hard(),
+                Some(TimeoutAction::Destroy),
+            ),
+        };
+        let start = StartServerPayload {
+            server: &StartOptions {
+                host: None,
+                avoid_host: Some(7653311107),
+            },
+        };
 
-    return Ok(json_response);
+        assert_eq!(
+            serde_json::to_value(stop).unwrap(),
+            json!({ "stop_server": { "stop_type": "soft", "timeout": "60" } })
+        );
+        assert_eq!(
+            serde_json::to_value(restart).unwrap(),
+            json!({ "restart_server": { "stop_type": "hard", "timeout": "0", "timeout_action": "destroy" } })
+        );
+        assert_eq!(
+            serde_json::to_value(start).unwrap(),
+            json!({ "server": { "avoid_host": 7653311107u64 } })
+        );
+    }
 }
@@ -0,0 +1,481 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::request;
+use crate::wait::{self, WaitOptions};
+use crate::wire;
+use crate::{Context, Error};
+
+/// How the data gets to UpCloud.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMethod {
+    /// UpCloud downloads the image from a URL.
+    HttpImport,
+    /// The image is uploaded to `StorageImport::direct_upload_url`.
+    DirectUpload,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportState {
+    /// Waiting for a direct upload to begin.
+    Prepared,
+    Pending,
+    Importing,
+    Failed,
+    Cancelling,
+    Cancelled,
+    Completed,
+    /// A state this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+impl ImportState {
+    /// Whether the import is over, one way or the other.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            ImportState::Failed | ImportState::Cancelled | ImportState::Completed
+        )
+    }
+}
+
+/// An import of data into a storage, as UpCloud reports it.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct StorageImport {
+    pub client_content_length: u64,
+    pub client_content_type: String,
+    #[serde(with = "wire::optional_rfc3339")]
+    pub completed: Option<DateTime<Utc>>,
+    #[serde(with = "wire::optional_rfc3339")]
+    pub created: Option<DateTime<Utc>>,
+    /// Where to upload the image to, for direct uploads.
+    pub direct_upload_url: String,
+    pub error_code: String,
+    pub error_message: String,
+    pub md5sum: String,
+    pub read_bytes: u64,
+    /// Hex encoded SHA-256 of the data UpCloud received.
+    pub sha256sum: String,
+    pub source: ImportMethod,
+    pub source_type: String,
+    pub state: ImportState,
+    pub uuid: String,
+    pub written_bytes: u64,
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct StorageImportPayload {
+    storage_import: StorageImport,
+}
+
+#[derive(Serialize, Debug)]
+struct StorageImportRequest<'a> {
+    source: ImportMethod,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_location: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+struct StorageImportRequestPayload<'a> {
+    storage_import: StorageImportRequest<'a>,
+}
+
+/// What to import into a storage with `storage::import`.
+#[derive(Debug, Clone)]
+pub enum ImportSource {
+    /// UpCloud downloads the image from this URL.
+    Url(String),
+    /// The image is read from a local file and uploaded. Files ending in
+    /// `.gz` and `.xz` are uploaded compressed for UpCloud to unpack, and
+    /// `.zst` files are unpacked on the fly, which needs the `zstd` feature.
+    /// Anything else is uploaded as is, so it must be a raw disk image.
+    File(PathBuf),
+}
+
+/// Called with the number of bytes transferred so far and the total, if
+/// known.
+pub type ProgressFn = dyn Fn(u64, Option<u64>) + Send + Sync;
+
+/// Options for `storage::import`.
+#[derive(Clone)]
+pub struct ImportOptions {
+    /// How long to wait for UpCloud to finish the import.
+    pub wait: WaitOptions,
+    /// Check that UpCloud got exactly the uploaded data. Only done for images
+    /// UpCloud doesn't decompress, as it hashes what it writes to the disk.
+    pub verify: bool,
+    progress: Option<Arc<ProgressFn>>,
+}
+
+impl ImportOptions {
+    pub fn new(wait: WaitOptions) -> ImportOptions {
+        ImportOptions {
+            wait,
+            verify: true,
+            progress: None,
+        }
+    }
+
+    pub fn verify(mut self, verify: bool) -> ImportOptions {
+        self.verify = verify;
+        self
+    }
+
+    /// Reports upload progress, or for URLs what UpCloud has read so far.
+    pub fn progress<F: Fn(u64, Option<u64>) + Send + Sync + 'static>(
+        mut self,
+        progress: F,
+    ) -> ImportOptions {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    fn report(&self, done: u64, total: Option<u64>) {
+        if let Some(progress) = &self.progress {
+            progress(done, total);
+        }
+    }
+}
+
+impl fmt::Debug for ImportOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImportOptions")
+            .field("wait", &self.wait)
+            .field("verify", &self.verify)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+/// POST /1.3/storage/{uuid}/import
+///
+/// With `ImportMethod::HttpImport`, `source_location` is the URL to download.
+pub async fn start_import(
+    ctx: &Context,
+    uuid: String,
+    source: ImportMethod,
+    source_location: Option<&str>,
+) -> Result<StorageImport, Error> {
+    let body = StorageImportRequestPayload {
+        storage_import: StorageImportRequest {
+            source,
+            source_location,
+        },
+    };
+    let response: StorageImportPayload =
+        request::post(ctx, &format!("/storage/{}/import", uuid), &body).await?;
+    Ok(response.storage_import)
+}
+
+/// GET /1.3/storage/{uuid}/import
+pub async fn get_import(ctx: &Context, uuid: String) -> Result<StorageImport, Error> {
+    let response: StorageImportPayload =
+        request::get(ctx, &format!("/storage/{}/import", uuid)).await?;
+    Ok(response.storage_import)
+}
+
+/// POST /1.3/storage/{uuid}/import/cancel
+pub async fn cancel_import(ctx: &Context, uuid: String) -> Result<StorageImport, Error> {
+    let response: StorageImportPayload =
+        request::post_empty(ctx, &format!("/storage/{}/import/cancel", uuid)).await?;
+    Ok(response.storage_import)
+}
+
+/// Imports an image into the storage `uuid`, overwriting what's on it, and
+/// waits until UpCloud is done with it.
+///
+/// The storage must be at least as big as the image and not attached to a
+/// running server.
+///
+/// If the upload fails or the wait runs out, the import is cancelled so the
+/// storage doesn't stay in maintenance.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use cloudup::storage::{ImportOptions, ImportSource};
+/// # async fn example(ctx: &cloudup::Context, uuid: String) -> Result<(), cloudup::Error> {
+/// let options = ImportOptions::new(cloudup::WaitOptions::new(Duration::from_secs(3600)))
+///     .progress(|sent, total| eprintln!("{} of {:?} bytes", sent, total));
+/// cloudup::storage::import(ctx, uuid, ImportSource::File("world.img.zst".into()), &options)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn import(
+    ctx: &Context,
+    uuid: String,
+    source: ImportSource,
+    options: &ImportOptions,
+) -> Result<StorageImport, Error> {
+    let (method, source_location, image) = match &source {
+        ImportSource::Url(url) => (ImportMethod::HttpImport, Some(url.as_str()), None),
+        ImportSource::File(path) => {
+            let format = ImageFormat::of(path);
+            // Fail before UpCloud starts waiting for data that never comes.
+            let (reader, total) = format.open(path)?;
+            let image = Image {
+                format,
+                reader,
+                total,
+            };
+            (ImportMethod::DirectUpload, None, Some(image))
+        }
+    };
+    let started = start_import(ctx, uuid.clone(), method, source_location).await?;
+    let result = transfer(ctx, &uuid, &started, image, options).await;
+    if result.is_err() {
+        // Otherwise the storage stays in maintenance until UpCloud gives up
+        // on the import. The error of the import is the one worth reporting.
+        cancel_import(ctx, uuid.clone()).await.ok();
+    }
+    let (finished, expected_hash) = result?;
+
+    match finished.state {
+        ImportState::Completed => {}
+        ImportState::Cancelled if finished.error_message.is_empty() => {
+            return Err(Error::ImportFailed {
+                code: finished.error_code,
+                message: String::from("the import was cancelled"),
+            })
+        }
+        _ => {
+            return Err(Error::ImportFailed {
+                code: finished.error_code,
+                message: finished.error_message,
+            })
+        }
+    }
+    if let Some(expected) = expected_hash.filter(|_| options.verify) {
+        if !expected.eq_ignore_ascii_case(&finished.sha256sum) {
+            return Err(Error::ChecksumMismatch {
+                expected,
+                actual: finished.sha256sum,
+            });
+        }
+    }
+    Ok(finished)
+}
+
+/// A local image, opened for uploading.
+struct Image {
+    format: ImageFormat,
+    reader: Box<dyn Read + Send>,
+    total: Option<u64>,
+}
+
+/// Uploads `image`, if there is one, and waits for the import to finish.
+/// Returns the finished import and the SHA-256 UpCloud should report for it,
+/// if known.
+async fn transfer(
+    ctx: &Context,
+    uuid: &str,
+    started: &StorageImport,
+    image: Option<Image>,
+    options: &ImportOptions,
+) -> Result<(StorageImport, Option<String>), Error> {
+    let uploaded = image.is_some();
+    let mut expected_hash = None;
+    if let Some(image) = image {
+        let sent_hash = upload(
+            ctx,
+            &started.direct_upload_url,
+            image.format,
+            image.reader,
+            image.total,
+            options,
+        )
+        .await?;
+        // UpCloud hashes what it writes to the disk, which for .gz and .xz
+        // images is the decompressed data we never see.
+        if !image.format.decompressed_by_upcloud() {
+            expected_hash = Some(sent_hash);
+        }
+    }
+
+    let finished = wait::until(
+        &options.wait,
+        std::future::pending(),
+        || get_import(ctx, String::from(uuid)),
+        |import| {
+            if !uploaded {
+                let total = Some(import.client_content_length).filter(|total| *total > 0);
+                options.report(import.read_bytes, total);
+            }
+            import.state.is_finished()
+        },
+    )
+    .await?;
+    Ok((finished, expected_hash))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageFormat {
+    Raw,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl ImageFormat {
+    fn of(path: &Path) -> ImageFormat {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => ImageFormat::Gzip,
+            Some("xz") => ImageFormat::Xz,
+            Some("zst") => ImageFormat::Zstd,
+            _ => ImageFormat::Raw,
+        }
+    }
+
+    /// Whether UpCloud decompresses the data before writing it to the disk.
+    fn decompressed_by_upcloud(&self) -> bool {
+        matches!(self, ImageFormat::Gzip | ImageFormat::Xz)
+    }
+
+    /// The `Content-Type` of the data as uploaded.
+    fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Gzip => "application/gzip",
+            ImageFormat::Xz => "application/x-xz",
+            ImageFormat::Raw | ImageFormat::Zstd => "application/octet-stream",
+        }
+    }
+
+    /// The data to upload and its size, if known up front.
+    fn open(&self, path: &Path) -> io::Result<(Box<dyn Read + Send>, Option<u64>)> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        match self {
+            #[cfg(feature = "zstd")]
+            ImageFormat::Zstd => Ok((Box::new(zstd::Decoder::new(file)?), None)),
+            #[cfg(not(feature = "zstd"))]
+            ImageFormat::Zstd => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "uploading .zst images needs the zstd feature of cloudup",
+            )),
+            _ => Ok((Box::new(file), Some(size))),
+        }
+    }
+}
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Streams `reader` to `url`, returning the hex encoded SHA-256 of what was
+/// sent.
+async fn upload(
+    ctx: &Context,
+    url: &str,
+    format: ImageFormat,
+    mut reader: Box<dyn Read + Send>,
+    total: Option<u64>,
+    options: &ImportOptions,
+) -> Result<String, Error> {
+    let (chunks, received) = tokio::sync::mpsc::channel::<io::Result<Vec<u8>>>(4);
+    let reporter = options.clone();
+    let reading = tokio::task::spawn_blocking(move || -> io::Result<String> {
+        let mut hasher = Sha256::new();
+        let mut sent = 0;
+        loop {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    let copy = io::Error::new(err.kind(), err.to_string());
+                    chunks.blocking_send(Err(copy)).ok();
+                    return Err(err);
+                }
+            };
+            chunk.truncate(read);
+            hasher.update(&chunk);
+            if chunks.blocking_send(Ok(chunk)).is_err() {
+                // The upload was given up on. Its error tells why.
+                break;
+            }
+            sent += read as u64;
+            reporter.report(sent, total);
+        }
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect())
+    });
+
+    let body = futures_util::stream::unfold(received, |mut received| async move {
+        received.recv().await.map(|chunk| (chunk, received))
+    });
+    let uploaded = request::upload(
+        ctx,
+        url,
+        format.content_type(),
+        reqwest::Body::wrap_stream(body),
+    )
+    .await;
+    let hash = reading.await.map_err(io::Error::from)??;
+    uploaded?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn storage_import() {
+        let import: StorageImport = serde_json::from_value(json!({
+            "client_content_length": 0,
+            "client_content_type": "",
+            "completed": "",
+            "created": "2021-06-14T12:06:47Z",
+            "direct_upload_url": "https://fi-hel1.img.upcloud.com/uploader/session/07a6c9a3-300e-4d0e-b935-624f3dbdff3f",
+            "error_code": "",
+            "error_message": "",
+            "md5sum": "",
+            "read_bytes": 0,
+            "sha256sum": "",
+            "source": "direct_upload",
+            "source_type": "",
+            "state": "prepared",
+            "uuid": "07a6c9a3-300e-4d0e-b935-624f3dbdff3f",
+            "written_bytes": 0
+        }))
+        .unwrap();
+
+        assert_eq!(import.source, ImportMethod::DirectUpload);
+        assert_eq!(import.state, ImportState::Prepared);
+        assert!(import.completed.is_none());
+        assert!(!import.state.is_finished());
+    }
+
+    #[test]
+    fn image_formats() {
+        assert_eq!(
+            ImageFormat::of(Path::new("world.img.xz")).content_type(),
+            "application/x-xz"
+        );
+        assert_eq!(
+            ImageFormat::of(Path::new("world.img.gz")),
+            ImageFormat::Gzip
+        );
+        assert_eq!(ImageFormat::of(Path::new("world.zst")), ImageFormat::Zstd);
+        assert_eq!(ImageFormat::of(Path::new("world.qcow2")), ImageFormat::Raw);
+    }
+}
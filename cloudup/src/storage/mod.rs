@@ -1,66 +1,724 @@
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-use crate::Context;
+use crate::request;
+use crate::server::{AccessType, ServerDetails, ServerDetailsResponse};
+use crate::wait::{self, WaitOptions};
+use crate::wire;
+use crate::{Context, Error};
 
-#[derive(Deserialize, Serialize, Debug)]
+mod import;
+mod resize;
+
+pub use import::{
+    cancel_import, get_import, import, start_import, ImportMethod, ImportOptions, ImportSource,
+    ImportState, ProgressFn, StorageImport,
+};
+pub use resize::{resize, resize_filesystem, ResizeOptions, ResizeReport};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageTier {
+    Maxiops,
+    Hdd,
+    Standard,
+    /// A tier this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageState {
+    Online,
+    /// The storage is being created, resized or otherwise changed.
+    Maintenance,
+    /// The storage is being cloned to another one.
+    Cloning,
+    /// A backup of the storage is being taken.
+    Backuping,
+    /// The storage is being synced with its backup or clone.
+    Syncing,
+    Error,
+    /// A state this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+/// When backups are taken: every day or on one day of the week.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupInterval {
+    Daily,
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+    /// An interval this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Scheduled backups of a single storage.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
+pub struct BackupRule {
+    pub interval: BackupInterval,
+    /// Time of day in UTC as `HHMM`.
+    pub time: String,
+    /// How many days the backups are kept, 1 to 1095.
+    #[serde(with = "wire::number_string")]
+    pub retention: u32,
+}
+
+impl BackupRule {
+    pub fn new(interval: BackupInterval, time: String, retention: u32) -> BackupRule {
+        BackupRule {
+            interval,
+            time,
+            retention,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageType {
+    /// A disk of a server.
+    Normal,
+    Backup,
+    Cdrom,
+    Template,
+    /// A type this library doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Which storages `storage::list` returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageFilter {
+    All,
+    /// Templates and CD-ROMs provided by UpCloud.
+    Public,
+    /// Everything the account owns.
+    Private,
+    Normal,
+    Backup,
+    Cdrom,
+    Template,
+    Favorite,
+}
+
+impl StorageFilter {
+    fn path(&self) -> &'static str {
+        match self {
+            StorageFilter::All => "/storage",
+            StorageFilter::Public => "/storage/public",
+            StorageFilter::Private => "/storage/private",
+            StorageFilter::Normal => "/storage/normal",
+            StorageFilter::Backup => "/storage/backup",
+            StorageFilter::Cdrom => "/storage/cdrom",
+            StorageFilter::Template => "/storage/template",
+            StorageFilter::Favorite => "/storage/favorite",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Label {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LabelsField {
+    pub label: Vec<Label>,
+}
+
+/// A storage as UpCloud lists them.
+///
+/// Listings of public storages leave out the fields that only make sense for
+/// the account's own storages.
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Storage {
-    access: String,
-    license: Decimal,
-    size: usize,
-    state: String,
+    pub access: AccessType,
+    #[serde(
+        default,
+        with = "wire::optional_rfc3339",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<LabelsField>,
+    pub license: Decimal,
+    /// The storage a backup was taken of or a clone was made from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// Size in gigabytes.
+    pub size: usize,
+    pub state: StorageState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<StorageTier>,
     pub title: String,
     pub uuid: String,
     #[serde(rename(serialize = "type", deserialize = "type"))]
-    storage_type: String,
+    pub storage_type: StorageType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
-#[serde(deny_unknown_fields)]
-struct GetTemplatesResponseStoragesField {
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct GetStoragesResponseStoragesField {
     storage: Vec<Storage>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct GetStoragesResponse {
+    storages: GetStoragesResponseStoragesField,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct StorageBackupsField {
+    pub backup: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct StorageServersField {
+    pub server: Vec<String>,
+}
+
+/// Everything UpCloud knows about a single storage.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+pub struct StorageDetails {
+    pub access: AccessType,
+    #[serde(default, with = "wire::empty_as_none")]
+    pub backup_rule: Option<BackupRule>,
+    /// UUIDs of the backups taken of the storage.
+    #[serde(default)]
+    pub backups: StorageBackupsField,
+    #[serde(
+        default,
+        with = "wire::optional_rfc3339",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created: Option<DateTime<Utc>>,
+    pub license: Decimal,
+    /// For backups and clones, the storage they were made of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// UUIDs of the servers the storage is attached to.
+    #[serde(default)]
+    pub servers: StorageServersField,
+    /// Size in gigabytes.
+    pub size: usize,
+    pub state: StorageState,
+    pub tier: StorageTier,
+    pub title: String,
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    pub storage_type: StorageType,
+    pub uuid: String,
+    pub zone: String,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct StorageDetailsResponse {
+    storage: StorageDetails,
+}
+
+/// Describes a storage to create with `storage::create`.
+///
+/// ```no_run
+/// # use cloudup::storage::*;
+/// let request = StorageCreateRequest::new(String::from("fi-hel1"), 100, String::from("World data"))
+///     .tier(StorageTier::Maxiops)
+///     .backup_rule(BackupRule::new(BackupInterval::Daily, String::from("0430"), 7));
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
-struct GetTemplatesResponse {
-    storages: GetTemplatesResponseStoragesField,
+pub struct StorageCreateRequest {
+    zone: String,
+    size: usize,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tier: Option<StorageTier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backup_rule: Option<BackupRule>,
 }
 
-/// GET /1.3/storage/template
-pub async fn get_templates(ctx: &Context) -> Result<Vec<Storage>, reqwest::Error> {
-    let url = format!("{}/1.3/storage/template", ctx.uc_baseurl);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .get(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let response: Result<GetTemplatesResponse, _> = match request {
-        Ok(response) => response.json::<GetTemplatesResponse>().await,
-        Err(error) => return Err(error),
-    };
-    return match response {
-        Ok(payload) => Ok(payload.storages.storage),
-        Err(err) => Err(err),
-    };
-}
-
-pub async fn delete(ctx: &Context, uuid: String) -> Result<(), reqwest::Error> {
-    let url = format!("{}/1.3/storage/{}", ctx.uc_baseurl, uuid);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .delete(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let result = match request {
-        Ok(response) => match response.status() {
-            reqwest::StatusCode::NO_CONTENT => Ok(()),
-            _ => panic!(),
+impl StorageCreateRequest {
+    /// A new empty storage of `size` gigabytes.
+    pub fn new(zone: String, size: usize, title: String) -> StorageCreateRequest {
+        StorageCreateRequest {
+            zone,
+            size,
+            title,
+            tier: None,
+            backup_rule: None,
+        }
+    }
+
+    /// Defaults to UpCloud's zone specific default tier when not given.
+    pub fn tier(mut self, tier: StorageTier) -> StorageCreateRequest {
+        self.tier = Some(tier);
+        self
+    }
+
+    pub fn backup_rule(mut self, rule: BackupRule) -> StorageCreateRequest {
+        self.backup_rule = Some(rule);
+        self
+    }
+}
+
+/// Changes to make with `storage::modify`. Only what's set is changed.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StorageModifyRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(
+        default,
+        with = "wire::optional_empty_as_none",
+        skip_serializing_if = "Option::is_none"
+    )]
+    backup_rule: Option<Option<BackupRule>>,
+}
+
+impl StorageModifyRequest {
+    /// Grows the storage to `size` gigabytes. Storages can't be shrunk.
+    ///
+    /// Only the storage itself grows; the partitions and filesystems on it
+    /// have to be resized separately.
+    pub fn size(mut self, size: usize) -> StorageModifyRequest {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn title(mut self, title: String) -> StorageModifyRequest {
+        self.title = Some(title);
+        self
+    }
+
+    pub fn backup_rule(mut self, rule: BackupRule) -> StorageModifyRequest {
+        self.backup_rule = Some(Some(rule));
+        self
+    }
+
+    /// Turns off scheduled backups of the storage.
+    pub fn clear_backup_rule(mut self) -> StorageModifyRequest {
+        self.backup_rule = Some(None);
+        self
+    }
+}
+
+/// Where and how to clone a storage with `storage::clone`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StorageCloneRequest {
+    zone: String,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tier: Option<StorageTier>,
+}
+
+impl StorageCloneRequest {
+    pub fn new(zone: String, title: String) -> StorageCloneRequest {
+        StorageCloneRequest {
+            zone,
+            title,
+            tier: None,
+        }
+    }
+
+    /// Defaults to the tier of the storage being cloned.
+    pub fn tier(mut self, tier: StorageTier) -> StorageCloneRequest {
+        self.tier = Some(tier);
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageDeviceType {
+    Disk,
+    Cdrom,
+}
+
+/// A storage to attach to a server with `storage::attach`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StorageAttachment {
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    device_type: StorageDeviceType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    storage: String,
+}
+
+impl StorageAttachment {
+    pub fn disk(storage: String) -> StorageAttachment {
+        StorageAttachment {
+            device_type: StorageDeviceType::Disk,
+            address: None,
+            storage,
+        }
+    }
+
+    pub fn cdrom(storage: String) -> StorageAttachment {
+        StorageAttachment {
+            device_type: StorageDeviceType::Cdrom,
+            address: None,
+            storage,
+        }
+    }
+
+    /// A bus (`virtio`, `scsi` or `ide`), or a bus and position such as
+    /// `virtio:1`. Defaults to the next free position on UpCloud's default bus.
+    pub fn address(mut self, address: String) -> StorageAttachment {
+        self.address = Some(address);
+        self
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct StoragePayload<'a, T> {
+    storage: &'a T,
+}
+
+#[derive(Serialize, Debug)]
+struct StorageDevicePayload<'a, T> {
+    storage_device: &'a T,
+}
+
+#[derive(Serialize, Debug)]
+struct TitleField {
+    title: String,
+}
+
+#[derive(Serialize, Debug)]
+struct DetachField<'a> {
+    address: &'a str,
+}
+
+/// GET /1.3/storage/{uuid}
+pub async fn get(ctx: &Context, uuid: String) -> Result<StorageDetails, Error> {
+    let response: StorageDetailsResponse = request::get(ctx, &format!("/storage/{}", uuid)).await?;
+    Ok(response.storage)
+}
+
+/// POST /1.3/storage -> Creates a new empty storage.
+pub async fn create(
+    ctx: &Context,
+    storage: &StorageCreateRequest,
+) -> Result<StorageDetails, Error> {
+    let response: StorageDetailsResponse =
+        request::post(ctx, "/storage", &StoragePayload { storage }).await?;
+    Ok(response.storage)
+}
+
+/// PUT /1.3/storage/{uuid} -> Resizes or renames a storage or changes its backup rule.
+///
+/// Growing a storage leaves its partitions as they were, see `storage::resize`.
+pub async fn modify(
+    ctx: &Context,
+    uuid: String,
+    changes: &StorageModifyRequest,
+) -> Result<StorageDetails, Error> {
+    let response: StorageDetailsResponse = request::put(
+        ctx,
+        &format!("/storage/{}", uuid),
+        &StoragePayload { storage: changes },
+    )
+    .await?;
+    Ok(response.storage)
+}
+
+/// POST /1.3/storage/{uuid}/clone -> Copies a storage, possibly to another zone.
+///
+/// The source stays in the `cloning` state until the copy is done.
+pub async fn clone(
+    ctx: &Context,
+    uuid: String,
+    target: &StorageCloneRequest,
+) -> Result<StorageDetails, Error> {
+    let response: StorageDetailsResponse = request::post(
+        ctx,
+        &format!("/storage/{}/clone", uuid),
+        &StoragePayload { storage: target },
+    )
+    .await?;
+    Ok(response.storage)
+}
+
+/// POST /1.3/server/{server_uuid}/storage/attach
+pub async fn attach(
+    ctx: &Context,
+    server_uuid: String,
+    attachment: &StorageAttachment,
+) -> Result<ServerDetails, Error> {
+    let response: ServerDetailsResponse = request::post(
+        ctx,
+        &format!("/server/{}/storage/attach", server_uuid),
+        &StorageDevicePayload {
+            storage_device: attachment,
+        },
+    )
+    .await?;
+    Ok(response.server)
+}
+
+/// POST /1.3/server/{server_uuid}/storage/detach -> Detaches whatever is at `address`, e.g. `virtio:1`.
+pub async fn detach(
+    ctx: &Context,
+    server_uuid: String,
+    address: String,
+) -> Result<ServerDetails, Error> {
+    let response: ServerDetailsResponse = request::post(
+        ctx,
+        &format!("/server/{}/storage/detach", server_uuid),
+        &StorageDevicePayload {
+            storage_device: &DetachField { address: &address },
+        },
+    )
+    .await?;
+    Ok(response.server)
+}
+
+/// POST /1.3/storage/{uuid}/backup -> Takes a backup of the storage right now.
+///
+/// The backup is a storage of its own with the type `backup`.
+pub async fn create_backup(
+    ctx: &Context,
+    uuid: String,
+    title: String,
+) -> Result<StorageDetails, Error> {
+    let response: StorageDetailsResponse = request::post(
+        ctx,
+        &format!("/storage/{}/backup", uuid),
+        &StoragePayload {
+            storage: &TitleField { title },
+        },
+    )
+    .await?;
+    Ok(response.storage)
+}
+
+/// POST /1.3/storage/{uuid}/restore -> Overwrites the storage a backup was
+/// taken of with the backup.
+///
+/// The storage must not be attached to a running server.
+pub async fn restore_backup(ctx: &Context, backup_uuid: String) -> Result<(), Error> {
+    request::post_empty_no_content(ctx, &format!("/storage/{}/restore", backup_uuid)).await
+}
+
+/// POST /1.3/storage/{uuid}/templatize -> Makes a template out of the storage,
+/// to create new servers from.
+///
+/// The storage must not be attached to a running server. UpCloud keeps both
+/// the storage and the template in `maintenance` for a while.
+pub async fn templatize(
+    ctx: &Context,
+    uuid: String,
+    title: String,
+) -> Result<StorageDetails, Error> {
+    let response: StorageDetailsResponse = request::post(
+        ctx,
+        &format!("/storage/{}/templatize", uuid),
+        &StoragePayload {
+            storage: &TitleField { title },
         },
-        Err(err) => Err(err),
-    };
-    return result;
+    )
+    .await?;
+    Ok(response.storage)
+}
+
+/// Polls the storage every five seconds until it reaches `state`.
+pub async fn wait_for_state(
+    ctx: &Context,
+    uuid: String,
+    state: StorageState,
+    timeout: Duration,
+) -> Result<StorageDetails, Error> {
+    wait_for_state_with(
+        ctx,
+        uuid,
+        state,
+        &WaitOptions::new(timeout),
+        std::future::pending(),
+    )
+    .await
+}
+
+/// Like `wait_for_state`, but with a custom poll interval and a `cancel`
+/// future that stops the wait with `Error::Cancelled` when it completes.
+pub async fn wait_for_state_with<C: Future<Output = ()>>(
+    ctx: &Context,
+    uuid: String,
+    state: StorageState,
+    options: &WaitOptions,
+    cancel: C,
+) -> Result<StorageDetails, Error> {
+    wait::until(
+        options,
+        cancel,
+        || get(ctx, uuid.clone()),
+        |storage| storage.state == state,
+    )
+    .await
+}
+
+/// Restores a backup to a new storage instead, leaving the original alone.
+pub async fn backup_to_new_storage(
+    ctx: &Context,
+    backup_uuid: String,
+    target: &StorageCloneRequest,
+) -> Result<StorageDetails, Error> {
+    clone(ctx, backup_uuid, target).await
+}
+
+/// The backups taken of the storage `uuid`, both scheduled and on-demand.
+pub async fn list_backups(ctx: &Context, uuid: String) -> Result<Vec<Storage>, Error> {
+    let mut backups = list(ctx, StorageFilter::Backup).await?;
+    backups.retain(|backup| backup.origin.as_ref() == Some(&uuid));
+    Ok(backups)
+}
+
+/// GET /1.3/storage/template
+pub async fn get_templates(ctx: &Context) -> Result<Vec<Storage>, Error> {
+    list(ctx, StorageFilter::Template).await
+}
+
+/// GET /1.3/storage/cdrom -> Lists the CD-ROM images, UpCloud's and the
+/// account's own.
+pub async fn get_cdroms(ctx: &Context) -> Result<Vec<Storage>, Error> {
+    list(ctx, StorageFilter::Cdrom).await
+}
+
+/// GET /1.3/storage/{filter} -> Lists the storages matching `filter`.
+pub async fn list(ctx: &Context, filter: StorageFilter) -> Result<Vec<Storage>, Error> {
+    let response: GetStoragesResponse = request::get(ctx, filter.path()).await?;
+    Ok(response.storages.storage)
+}
+
+/// The account's disks that aren't attached to any server, such as the ones
+/// left behind by `server::delete`.
+///
+/// Looks up every private disk separately, so this takes a while on
+/// accounts with lots of storages.
+pub async fn list_detached(ctx: &Context) -> Result<Vec<StorageDetails>, Error> {
+    let mut detached = vec![];
+    for storage in list(ctx, StorageFilter::Normal).await? {
+        if storage.access != AccessType::Private {
+            continue;
+        }
+        let details = get(ctx, storage.uuid).await?;
+        if details.servers.server.is_empty() {
+            detached.push(details);
+        }
+    }
+    Ok(detached)
+}
+
+/// DELETE /1.3/storage/{uuid}
+pub async fn delete(ctx: &Context, uuid: String) -> Result<(), Error> {
+    request::delete(ctx, &format!("/storage/{}", uuid)).await
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn modify_request_wire_format() {
+        let clear = StorageModifyRequest::default().clear_backup_rule();
+        let resize = StorageModifyRequest::default().size(20);
+
+        assert_eq!(
+            serde_json::to_value(&StoragePayload { storage: &clear }).unwrap(),
+            json!({ "storage": { "backup_rule": "" } })
+        );
+        assert_eq!(
+            serde_json::to_value(&StoragePayload { storage: &resize }).unwrap(),
+            json!({ "storage": { "size": 20 } })
+        );
+    }
+
+    #[test]
+    fn create_request_wire_format() {
+        let request = StorageCreateRequest::new(String::from("fi-hel1"), 100, String::from("Data"))
+            .tier(StorageTier::Hdd)
+            .backup_rule(BackupRule::new(
+                BackupInterval::Daily,
+                String::from("0430"),
+                7,
+            ));
+
+        assert_eq!(
+            serde_json::to_value(&StoragePayload { storage: &request }).unwrap(),
+            json!({
+                "storage": {
+                    "zone": "fi-hel1",
+                    "size": 100,
+                    "title": "Data",
+                    "tier": "hdd",
+                    "backup_rule": { "interval": "daily", "time": "0430", "retention": "7" }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn storage_details() {
+        let body = json!({
+            "storage": {
+                "access": "private",
+                "backup_rule": "",
+                "backups": { "backup": [] },
+                "license": 0,
+                "servers": { "server": ["0077fa3d-32db-4b09-9f5f-30d9e9afb565"] },
+                "size": 10,
+                "state": "online",
+                "tier": "maxiops",
+                "title": "Operating system disk",
+                "type": "normal",
+                "uuid": "01d4fcd4-e446-433b-8a9c-551a1284952e",
+                "zone": "fi-hel1"
+            }
+        });
+
+        let details = serde_json::from_value::<StorageDetailsResponse>(body)
+            .unwrap()
+            .storage;
+
+        assert_eq!(details.backup_rule, None);
+        assert_eq!(details.state, StorageState::Online);
+        assert_eq!(details.servers.server.len(), 1);
+    }
 }
@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+
+use super::{get, modify, wait_for_state_with, StorageDetails, StorageModifyRequest, StorageState};
+use crate::request;
+use crate::server::{self, ServerState, StartOptions, StopOptions};
+use crate::wait::{self, WaitOptions};
+use crate::{Context, Error};
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct ResizeBackupResponse {
+    resize_backup: StorageDetails,
+}
+
+/// POST /1.3/storage/{uuid}/resize -> Grows the last partition and its
+/// filesystem to fill the storage, after `storage::modify` has grown it.
+///
+/// UpCloud takes a backup of the storage first and returns it. The backup
+/// isn't deleted automatically. The storage must not be attached to a
+/// running server.
+pub async fn resize_filesystem(ctx: &Context, uuid: String) -> Result<StorageDetails, Error> {
+    let response: ResizeBackupResponse =
+        request::post_empty(ctx, &format!("/storage/{}/resize", uuid)).await?;
+    Ok(response.resize_backup)
+}
+
+/// How `storage::resize` goes about it.
+#[derive(Debug, Clone)]
+pub struct ResizeOptions {
+    pub stop: StopOptions,
+    /// For each wait: for the servers to stop and for the storage to come
+    /// back online.
+    pub wait: WaitOptions,
+    /// Start the servers that were running to begin with again afterwards,
+    /// whether the resize worked or not. On by default.
+    pub restart: bool,
+}
+
+impl ResizeOptions {
+    pub fn new(wait: WaitOptions) -> ResizeOptions {
+        ResizeOptions {
+            stop: StopOptions::default(),
+            wait,
+            restart: true,
+        }
+    }
+
+    pub fn stop(mut self, stop: StopOptions) -> ResizeOptions {
+        self.stop = stop;
+        self
+    }
+
+    pub fn restart(mut self, restart: bool) -> ResizeOptions {
+        self.restart = restart;
+        self
+    }
+}
+
+/// What `storage::resize` did.
+#[derive(Debug)]
+pub struct ResizeReport {
+    /// The storage at its new size.
+    pub storage: StorageDetails,
+    /// UUID of the backup UpCloud took before touching the filesystem, to
+    /// delete once the resize turns out fine.
+    pub backup: String,
+    /// The servers that were stopped for the resize. Running again unless
+    /// `restart` was turned off.
+    pub stopped: Vec<String>,
+}
+
+/// Grows a storage to `size` gigabytes along with its last partition and
+/// filesystem, stopping the servers it's attached to for the duration.
+///
+/// If anything fails halfway, the servers are still started again as far as
+/// possible, and the error of the resize is returned. Otherwise, if a server
+/// fails to start, the others are still started and the first error is
+/// returned.
+///
+/// ```no_run
+/// # async fn example(ctx: cloudup::Context, uuid: String) -> Result<(), cloudup::Error> {
+/// use std::time::Duration;
+/// use cloudup::storage::{self, ResizeOptions};
+///
+/// let options = ResizeOptions::new(cloudup::WaitOptions::new(Duration::from_secs(600)));
+/// let report = storage::resize(&ctx, uuid, 100, &options).await?;
+/// storage::delete(&ctx, report.backup).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn resize(
+    ctx: &Context,
+    uuid: String,
+    size: usize,
+    options: &ResizeOptions,
+) -> Result<ResizeReport, Error> {
+    let mut stopped = vec![];
+    let result = stop_and_grow(ctx, &uuid, size, options, &mut stopped).await;
+
+    let mut restarted = Ok(());
+    if options.restart {
+        if result.is_err() {
+            // The servers can't start while the storage is still busy.
+            wait_until_online(ctx, &uuid, options).await.ok();
+        }
+        for server_uuid in &stopped {
+            let started = server::start(ctx, server_uuid.clone(), &StartOptions::default()).await;
+            // One server failing to start doesn't keep the rest stopped.
+            if let (Err(err), Ok(())) = (started, &restarted) {
+                restarted = Err(err);
+            }
+        }
+    }
+    // After a failed resize, its error is the one worth reporting.
+    let (storage, backup) = result?;
+    restarted?;
+    Ok(ResizeReport {
+        storage,
+        backup: backup.uuid,
+        stopped,
+    })
+}
+
+/// Stops the running servers of the storage, adding them to `stopped`, and
+/// grows the storage and its filesystem.
+async fn stop_and_grow(
+    ctx: &Context,
+    uuid: &str,
+    size: usize,
+    options: &ResizeOptions,
+    stopped: &mut Vec<String>,
+) -> Result<(StorageDetails, StorageDetails), Error> {
+    for server_uuid in get(ctx, String::from(uuid)).await?.servers.server {
+        // Let whatever the server is in the middle of finish first.
+        let server = wait::until(
+            &options.wait,
+            std::future::pending(),
+            || server::get_server(ctx, server_uuid.clone()),
+            |server| server.state != ServerState::Maintenance,
+        )
+        .await?;
+        if server.state == ServerState::Started {
+            server::stop(ctx, server_uuid.clone(), &options.stop).await?;
+            stopped.push(server_uuid);
+        }
+    }
+    for server_uuid in stopped.iter() {
+        server::wait_for_state_with(
+            ctx,
+            server_uuid.clone(),
+            ServerState::Stopped,
+            &options.wait,
+            std::future::pending(),
+        )
+        .await?;
+    }
+
+    modify(
+        ctx,
+        String::from(uuid),
+        &StorageModifyRequest::default().size(size),
+    )
+    .await?;
+    wait_until_online(ctx, uuid, options).await?;
+    let backup = resize_filesystem(ctx, String::from(uuid)).await?;
+    let storage = wait_until_online(ctx, uuid, options).await?;
+    Ok((storage, backup))
+}
+
+async fn wait_until_online(
+    ctx: &Context,
+    uuid: &str,
+    options: &ResizeOptions,
+) -> Result<StorageDetails, Error> {
+    wait_for_state_with(
+        ctx,
+        String::from(uuid),
+        StorageState::Online,
+        &options.wait,
+        std::future::pending(),
+    )
+    .await
+}
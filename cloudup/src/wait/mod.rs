@@ -0,0 +1,62 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::Error;
+
+/// How long and how often to poll the API while waiting for something.
+#[derive(Debug, Clone)]
+pub struct WaitOptions {
+    /// Give up with `Error::Timeout` after this long.
+    pub timeout: Duration,
+    /// Delay between two polls.
+    pub poll_interval: Duration,
+}
+
+impl WaitOptions {
+    pub fn new(timeout: Duration) -> WaitOptions {
+        WaitOptions {
+            timeout,
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+
+    pub fn poll_interval(mut self, poll_interval: Duration) -> WaitOptions {
+        self.poll_interval = poll_interval;
+        self
+    }
+}
+
+/// Calls `fetch` until `done` accepts what it returned.
+///
+/// Fails with `Error::Timeout` when `options.timeout` runs out and with
+/// `Error::Cancelled` as soon as `cancel` completes. Errors from `fetch` are
+/// returned as is.
+pub(crate) async fn until<T, Fetch, Fut, Done, Cancel>(
+    options: &WaitOptions,
+    cancel: Cancel,
+    mut fetch: Fetch,
+    done: Done,
+) -> Result<T, Error>
+where
+    Fetch: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+    Done: Fn(&T) -> bool,
+    Cancel: Future<Output = ()>,
+{
+    let polling = async {
+        loop {
+            let current = fetch().await?;
+            if done(&current) {
+                return Ok(current);
+            }
+            tokio::time::sleep(options.poll_interval).await;
+        }
+    };
+
+    tokio::select! {
+        result = tokio::time::timeout(options.timeout, polling) => {
+            result.unwrap_or(Err(Error::Timeout(options.timeout)))
+        }
+        _ = cancel => Err(Error::Cancelled),
+    }
+}
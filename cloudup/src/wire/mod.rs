@@ -0,0 +1,462 @@
+//! Serde adapters between UpCloud's wire format and proper Rust types.
+//!
+//! UpCloud sends flags as `"yes"`/`"no"` or `"on"`/`"off"`, some numbers as
+//! strings and timestamps as seconds since the epoch. Use these with
+//! `#[serde(with = "...")]`.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::Serializer;
+
+fn parse_flag<E: de::Error>(value: &str, on: &str, off: &str) -> Result<bool, E> {
+    if value == on {
+        Ok(true)
+    } else if value == off {
+        Ok(false)
+    } else {
+        Err(E::custom(format!(
+            "expected \"{}\" or \"{}\", got \"{}\"",
+            on, off, value
+        )))
+    }
+}
+
+/// `bool` as `"yes"`/`"no"`.
+pub(crate) mod yes_no {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(if *value { "yes" } else { "no" })
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<bool, D::Error> {
+        parse_flag(&String::deserialize(deserializer)?, "yes", "no")
+    }
+}
+
+/// `Option<bool>` as `"yes"`/`"no"`. Pair with `default` and
+/// `skip_serializing_if = "Option::is_none"`.
+pub(crate) mod optional_yes_no {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<bool>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => yes_no::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<bool>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) => parse_flag(&value, "yes", "no").map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// `bool` as `"on"`/`"off"`.
+pub(crate) mod on_off {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(if *value { "on" } else { "off" })
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<bool, D::Error> {
+        parse_flag(&String::deserialize(deserializer)?, "on", "off")
+    }
+}
+
+/// `Option<bool>` as `"on"`/`"off"`.
+pub(crate) mod optional_on_off {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<bool>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => on_off::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<bool>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) => parse_flag(&value, "on", "off").map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(serde_json::Number),
+}
+
+/// `bool` as `"1"`/`"0"`. Plain numbers are accepted too.
+pub(crate) mod one_zero {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(if *value { "1" } else { "0" })
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<bool, D::Error> {
+        let value = match StringOrNumber::deserialize(deserializer)? {
+            StringOrNumber::String(value) => value,
+            StringOrNumber::Number(value) => value.to_string(),
+        };
+        parse_flag(&value, "1", "0")
+    }
+}
+
+/// A number sent as a string. Plain numbers are accepted too.
+pub(crate) mod number_string {
+    use super::*;
+
+    pub(crate) fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let value = match StringOrNumber::deserialize(deserializer)? {
+            StringOrNumber::String(value) => value,
+            StringOrNumber::Number(value) => value.to_string(),
+        };
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+/// `DateTime<Utc>` as seconds since the epoch.
+pub(crate) mod timestamp {
+    use super::*;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(value.timestamp())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let seconds = i64::deserialize(deserializer)?;
+        Utc.timestamp_opt(seconds, 0)
+            .single()
+            .ok_or_else(|| de::Error::custom(format!("timestamp {} is out of range", seconds)))
+    }
+}
+
+/// `Option<DateTime<Utc>>` as seconds since the epoch.
+pub(crate) mod optional_timestamp {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => timestamp::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Wrapper(#[serde(with = "timestamp")] DateTime<Utc>);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+}
+
+/// `Option<DateTime<Utc>>` as an RFC 3339 string such as `2020-08-27T11:05:19Z`.
+/// An empty string counts as none.
+pub(crate) mod optional_rfc3339 {
+    use super::*;
+    use chrono::{DateTime, SecondsFormat, Utc};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => {
+                serializer.serialize_str(&value.to_rfc3339_opts(SecondsFormat::Secs, true))
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) if value.is_empty() => Ok(None),
+            Some(value) => DateTime::parse_from_rfc3339(&value)
+                .map(|value| Some(value.with_timezone(&Utc)))
+                .map_err(de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+/// `Option<T>` sent as an empty string when there's nothing.
+pub(crate) mod empty_as_none {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum EmptyOr<T> {
+        Empty(String),
+        Value(T),
+    }
+
+    pub(crate) fn serialize<T: Serialize, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_str(""),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        match EmptyOr::deserialize(deserializer)? {
+            EmptyOr::Empty(value) if value.is_empty() => Ok(None),
+            EmptyOr::Empty(value) => Err(de::Error::custom(format!(
+                "expected an object or an empty string, got \"{}\"",
+                value
+            ))),
+            EmptyOr::Value(value) => Ok(Some(value)),
+        }
+    }
+}
+
+/// A change to an `empty_as_none` field: `None` leaves it as it is and
+/// `Some(None)` clears it with an empty string. Pair with `default` and
+/// `skip_serializing_if = "Option::is_none"`.
+pub(crate) mod optional_empty_as_none {
+    use super::*;
+    use serde::Serialize;
+
+    pub(crate) fn serialize<T: Serialize, S: Serializer>(
+        value: &Option<Option<T>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => empty_as_none::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        empty_as_none::deserialize(deserializer).map(Some)
+    }
+}
+
+/// `Option<T>` sent as a string, such as a port number, a comment or a
+/// protocol name. An empty string counts as none. Numbers inside the string
+/// are parsed, and plain numbers are accepted too.
+pub(crate) mod optional_string {
+    use super::*;
+    use serde::de::{DeserializeOwned, IntoDeserializer};
+    use serde::{ser, Serialize};
+    use serde_json::Value;
+
+    pub(crate) fn serialize<T: Serialize, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let value = match value {
+            Some(value) => serde_json::to_value(value).map_err(ser::Error::custom)?,
+            None => return serializer.serialize_str(""),
+        };
+        match value {
+            Value::Number(number) => serializer.collect_str(&number),
+            value => value.serialize(serializer),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: DeserializeOwned,
+        D: Deserializer<'de>,
+    {
+        let value = match StringOrNumber::deserialize(deserializer)? {
+            StringOrNumber::String(value) if value.is_empty() => return Ok(None),
+            StringOrNumber::String(value) => value,
+            StringOrNumber::Number(value) => value.to_string(),
+        };
+        let from_str: Result<T, de::value::Error> =
+            T::deserialize(value.as_str().into_deserializer());
+        match from_str {
+            Ok(parsed) => Ok(Some(parsed)),
+            Err(_) => serde_json::from_str(&value)
+                .map(Some)
+                .map_err(de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Flags {
+        #[serde(with = "super::yes_no")]
+        metadata: bool,
+        #[serde(with = "super::on_off")]
+        firewall: bool,
+        #[serde(
+            default,
+            with = "super::optional_yes_no",
+            skip_serializing_if = "Option::is_none"
+        )]
+        floating: Option<bool>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Numbers {
+        #[serde(with = "super::number_string")]
+        core_number: u32,
+        #[serde(with = "super::number_string")]
+        memory_amount: u64,
+        #[serde(with = "super::timestamp")]
+        created: DateTime<Utc>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Strings {
+        #[serde(default, with = "super::optional_string")]
+        port: Option<u16>,
+        #[serde(default, with = "super::optional_string")]
+        comment: Option<String>,
+    }
+
+    #[test]
+    fn flags_round_trip() {
+        let wire = json!({ "metadata": "yes", "firewall": "off" });
+        let flags: Flags = serde_json::from_value(wire.clone()).unwrap();
+
+        assert_eq!(
+            flags,
+            Flags {
+                metadata: true,
+                firewall: false,
+                floating: None
+            }
+        );
+        assert_eq!(serde_json::to_value(&flags).unwrap(), wire);
+    }
+
+    #[test]
+    fn rejects_unknown_flag_values() {
+        let wire = json!({ "metadata": "maybe", "firewall": "on" });
+
+        assert!(serde_json::from_value::<Flags>(wire).is_err());
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct BootDisk {
+        #[serde(with = "super::one_zero")]
+        boot_disk: bool,
+    }
+
+    #[test]
+    fn one_zero_flags_round_trip() {
+        let wire = json!({ "boot_disk": "1" });
+        let flag: BootDisk = serde_json::from_value(wire.clone()).unwrap();
+
+        assert!(flag.boot_disk);
+        assert_eq!(serde_json::to_value(&flag).unwrap(), wire);
+        assert!(
+            !serde_json::from_value::<BootDisk>(json!({ "boot_disk": 0 }))
+                .unwrap()
+                .boot_disk
+        );
+        assert!(serde_json::from_value::<BootDisk>(json!({ "boot_disk": "2" })).is_err());
+    }
+
+    #[test]
+    fn numbers_round_trip() {
+        let wire = json!({ "core_number": "2", "memory_amount": "4096", "created": 1598526319 });
+        let numbers: Numbers = serde_json::from_value(wire.clone()).unwrap();
+
+        assert_eq!(numbers.core_number, 2);
+        assert_eq!(numbers.memory_amount, 4096);
+        assert_eq!(numbers.created.to_rfc3339(), "2020-08-27T11:05:19+00:00");
+        assert_eq!(serde_json::to_value(&numbers).unwrap(), wire);
+    }
+
+    #[test]
+    fn accepts_plain_numbers() {
+        let wire = json!({ "core_number": 2, "memory_amount": 4096, "created": 0 });
+        let numbers: Numbers = serde_json::from_value(wire).unwrap();
+
+        assert_eq!(numbers.core_number, 2);
+    }
+
+    #[test]
+    fn optional_strings_round_trip() {
+        let wire = json!({ "port": "25565", "comment": "Minecraft" });
+        let strings: Strings = serde_json::from_value(wire.clone()).unwrap();
+
+        assert_eq!(strings.port, Some(25565));
+        assert_eq!(strings.comment.as_deref(), Some("Minecraft"));
+        assert_eq!(serde_json::to_value(&strings).unwrap(), wire);
+
+        let empty: Strings = serde_json::from_value(json!({ "port": "", "comment": "" })).unwrap();
+        assert_eq!(
+            empty,
+            Strings {
+                port: None,
+                comment: None
+            }
+        );
+    }
+}
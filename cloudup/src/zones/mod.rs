@@ -1,40 +1,36 @@
-use crate::Context;
+use crate::request;
+use crate::wire;
+use crate::{Context, Error};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 pub struct Zone {
-    description: String,
-    id: String,
-    public: String,
+    pub description: String,
+    pub id: String,
+    #[serde(with = "wire::yes_no")]
+    pub public: bool,
+    /// Fields this version of the library doesn't know about yet.
+    #[cfg(feature = "lenient")]
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
 struct GetZonesResponseZoneField {
     zone: Vec<Zone>,
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
-pub struct GetZonesResponse {
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(not(feature = "lenient"), serde(deny_unknown_fields))]
+struct GetZonesResponse {
     zones: GetZonesResponseZoneField,
 }
 
-pub async fn fetch_zones(ctx: &Context) -> Result<GetZonesResponse, reqwest::Error> {
-    let url = format!("{}/1.3/zone", ctx.uc_baseurl);
-    let client = reqwest::Client::new();
-    let request: Result<reqwest::Response, reqwest::Error> = client
-        .get(&url)
-        .basic_auth(ctx.credentials.user.clone(), ctx.credentials.pass.clone())
-        .send()
-        .await;
-    let payload = match request {
-        Ok(response) => response.json::<GetZonesResponse>(),
-        Err(error) => {
-            panic!("{}", error);
-        }
-    };
-    payload.await
+/// GET /1.3/zone -> Lists the available zones.
+pub async fn fetch_zones(ctx: &Context) -> Result<Vec<Zone>, Error> {
+    let response: GetZonesResponse = request::get(ctx, "/zone").await?;
+    Ok(response.zones.zone)
 }
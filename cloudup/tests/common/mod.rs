@@ -0,0 +1,37 @@
+#![allow(dead_code)]
+
+use serde_json::json;
+
+pub const SERVER_UUID: &str = "0077fa3d-32db-4b09-9f5f-30d9e9afb565";
+
+/// The response to GET /1.3/server/{SERVER_UUID} with the server in `state`.
+pub fn server_json(state: &str) -> serde_json::Value {
+    json!({
+        "server": {
+            "boot_order": "disk",
+            "core_number": "1",
+            "created": 1598526319,
+            "firewall": "off",
+            "hostname": "server1.example.com",
+            "ip_addresses": { "ip_address": [] },
+            "license": 0,
+            "memory_amount": "1024",
+            "metadata": "yes",
+            "networking": { "interfaces": { "interface": [] } },
+            "nic_model": "virtio",
+            "plan": "1xCPU-1GB",
+            "plan_ipv4_bytes": "0",
+            "plan_ipv6_bytes": "0",
+            "remote_access_enabled": "no",
+            "simple_backup": "no",
+            "state": state,
+            "storage_devices": { "storage_device": [] },
+            "tags": { "tag": [] },
+            "timezone": "UTC",
+            "title": "server1.example.com",
+            "uuid": SERVER_UUID,
+            "video_model": "vga",
+            "zone": "fi-hel1"
+        }
+    })
+}
@@ -0,0 +1,1422 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use cloudup::accounts::{self, AccountSettings, Role};
+use cloudup::backups::{self, RetentionPolicy};
+use cloudup::error::ErrorCode;
+use cloudup::firewall::{self, FirewallAction, FirewallDirection, FirewallProtocol, FirewallRule};
+use cloudup::floating_ip;
+use cloudup::ip_addresses::{self, IpAddressRequest};
+use cloudup::networks::{self, IpNetwork, NetworkCreateRequest};
+use cloudup::permissions::{self, Permission};
+use cloudup::server::{
+    self, AccessType, BakeOptions, BootOrder, InterfaceType, IpAddrTypePair, IpAddressSpecs,
+    IpFamily, LoginUser, ServerCreateRequest, ServerState, StopOptions, StorageDevice,
+};
+use cloudup::storage::{
+    self, BackupInterval, BackupRule, ImportMethod, ImportOptions, ImportSource, ImportState,
+    ResizeOptions, StorageAttachment, StorageCloneRequest, StorageCreateRequest, StorageFilter,
+    StorageModifyRequest, StorageState, StorageTier, StorageType,
+};
+use cloudup::{Context, Error, RetryPolicy};
+use cloudup_mock::{Failure, MockUpCloud};
+
+fn context(mock: &MockUpCloud) -> Context {
+    Context::builder(mock.username(), mock.password())
+        .base_url(mock.uri())
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap()
+}
+
+fn minecraft_server(template_uuid: String) -> ServerCreateRequest {
+    ServerCreateRequest::new(
+        String::from("fi-hel1"),
+        String::from("Minecraft"),
+        String::from("minecraft.example.com"),
+    )
+    .plan(String::from("2xCPU-4GB"))
+    .storage_device(StorageDevice::clone_from(template_uuid, 80).tier(StorageTier::Maxiops))
+    .interface(IpAddrTypePair::public(IpFamily::IPv4))
+    .interface(IpAddrTypePair::public(IpFamily::IPv6))
+    .login_user(LoginUser::new(String::from("steve"), vec![]))
+}
+
+#[tokio::test]
+async fn lists_catalogue() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+
+    let plans = cloudup::plan::get_plans(&ctx).await.unwrap();
+    let zones = cloudup::zones::fetch_zones(&ctx).await.unwrap();
+    let prices = cloudup::pricing::get_pricing(&ctx).await.unwrap();
+    let templates = storage::get_templates(&ctx).await.unwrap();
+    cloudup::accounts::get_account_info(&ctx).await.unwrap();
+    let accounts = cloudup::accounts::get_account_list(&ctx).await.unwrap();
+
+    assert!(plans.iter().any(|plan| plan.name == "2xCPU-4GB"));
+    assert!(zones.iter().any(|zone| zone.id == "fi-hel1"));
+    assert_eq!(prices.len(), zones.len());
+    assert!(templates
+        .iter()
+        .any(|template| template.title == "CentOS 8"));
+    assert_eq!(accounts.len(), 1);
+}
+
+#[tokio::test]
+async fn sub_account_lifecycle() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let settings = AccountSettings::default()
+        .email(String::from("ci@example.com"))
+        .role(Role::Technical)
+        .allow_gui(false)
+        .tag_access(String::from("CI"));
+
+    let created = accounts::create_sub_account(
+        &ctx,
+        String::from("ci"),
+        String::from("hunter22"),
+        &settings,
+    )
+    .await
+    .unwrap();
+    assert_eq!(created.account_type, "sub");
+    assert_eq!(created.main_account, mock.username());
+    assert!(!created.allow_gui);
+    assert_eq!(created.tag_access.tag, vec!["CI"]);
+
+    let modified = accounts::modify_account(
+        &ctx,
+        String::from("ci"),
+        &AccountSettings::default().ip_filter(String::from("192.0.2.10")),
+    )
+    .await
+    .unwrap();
+    assert_eq!(modified.ip_filters.ip_filter, vec!["192.0.2.10"]);
+    assert_eq!(modified.email, "ci@example.com");
+
+    let fetched = accounts::get_account_details(&ctx, String::from("ci"))
+        .await
+        .unwrap();
+    assert_eq!(fetched.ip_filters.ip_filter, vec!["192.0.2.10"]);
+    assert_eq!(accounts::get_account_list(&ctx).await.unwrap().len(), 2);
+
+    accounts::delete_sub_account(&ctx, String::from("ci"))
+        .await
+        .unwrap();
+    let err = accounts::get_account_details(&ctx, String::from("ci"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::AccountNotFound));
+}
+
+#[tokio::test]
+async fn scopes_permissions_to_a_tag() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let mut uuids = vec![];
+    for tag in &["CI", "CI", "PROD"] {
+        let request = minecraft_server(template.clone()).tag(String::from(*tag));
+        uuids.push(server::create(&ctx, &request).await.unwrap().uuid);
+    }
+    let user = String::from("ci");
+    accounts::create_sub_account(
+        &ctx,
+        user.clone(),
+        String::from("hunter22"),
+        &AccountSettings::default(),
+    )
+    .await
+    .unwrap();
+    for uuid in &[&uuids[0], &uuids[2]] {
+        let permission = Permission::server(user.clone(), uuid.to_string()).with_storage(true);
+        permissions::grant(&ctx, &permission).await.unwrap();
+    }
+
+    let changes = permissions::scope_to_tag(&ctx, user.clone(), String::from("CI"), false)
+        .await
+        .unwrap();
+
+    assert_eq!(changes.revoked.len(), 2);
+    assert_eq!(changes.granted.len(), 2);
+    let mut scoped: Vec<(String, Option<bool>)> = permissions::list_for(&ctx, user.clone())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|permission| (permission.target_identifier, permission.options.storage))
+        .collect();
+    scoped.sort();
+    assert_eq!(
+        scoped,
+        vec![
+            (uuids[0].clone(), Some(false)),
+            (uuids[1].clone(), Some(false))
+        ]
+    );
+
+    let again = permissions::scope_to_tag(&ctx, user, String::from("CI"), false)
+        .await
+        .unwrap();
+    assert!(again.granted.is_empty() && again.revoked.is_empty());
+}
+
+#[tokio::test]
+async fn server_lifecycle() {
+    let mock = MockUpCloud::start().await;
+    mock.set_transition_delay(Duration::from_millis(100));
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+
+    let created = server::create(&ctx, &minecraft_server(template.clone()))
+        .await
+        .unwrap();
+    assert_eq!(created.state, ServerState::Maintenance);
+    assert_eq!(created.ip_addresses.ip_address.len(), 2);
+    assert_eq!(created.storage_devices.storage_device[0].storage_size, 80);
+
+    let options =
+        cloudup::WaitOptions::new(Duration::from_secs(5)).poll_interval(Duration::from_millis(20));
+    server::wait_for_state_with(
+        &ctx,
+        created.uuid.clone(),
+        ServerState::Started,
+        &options,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(server::get_servers(&ctx).await.unwrap().len(), 1);
+
+    server::stop(&ctx, created.uuid.clone(), &StopOptions::default())
+        .await
+        .unwrap();
+    server::wait_for_state_with(
+        &ctx,
+        created.uuid.clone(),
+        ServerState::Stopped,
+        &options,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+
+    server::delete_along_storage(&ctx, created.uuid.clone())
+        .await
+        .unwrap();
+    assert!(server::get_servers(&ctx).await.unwrap().is_empty());
+    let disk = created.storage_devices.storage_device[0].storage.clone();
+    assert!(!mock.state().storages.contains_key(&disk));
+    assert!(mock.state().storages.contains_key(&template));
+}
+
+#[tokio::test]
+async fn storage_lifecycle() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+
+    let request = StorageCreateRequest::new(String::from("fi-hel1"), 50, String::from("World"))
+        .tier(StorageTier::Hdd)
+        .backup_rule(BackupRule::new(
+            BackupInterval::Daily,
+            String::from("0430"),
+            7,
+        ));
+    let created = storage::create(&ctx, &request).await.unwrap();
+    assert_eq!(created.tier, StorageTier::Hdd);
+    assert_eq!(created.backup_rule.unwrap().retention, 7);
+
+    let attached = storage::attach(
+        &ctx,
+        server.uuid.clone(),
+        &StorageAttachment::disk(created.uuid.clone()),
+    )
+    .await
+    .unwrap();
+    let device = attached.storage_devices.storage_device.last().unwrap();
+    assert_eq!(device.storage, created.uuid);
+    assert_eq!(device.address, "virtio:1");
+    let fetched = storage::get(&ctx, created.uuid.clone()).await.unwrap();
+    assert_eq!(fetched.servers.server, vec![server.uuid.clone()]);
+
+    let modified = storage::modify(
+        &ctx,
+        created.uuid.clone(),
+        &StorageModifyRequest::default()
+            .size(100)
+            .title(String::from("Worlds")),
+    )
+    .await
+    .unwrap();
+    assert_eq!(modified.size, 100);
+    assert_eq!(modified.title, "Worlds");
+    assert!(modified.backup_rule.is_some());
+    let cleared = storage::modify(
+        &ctx,
+        created.uuid.clone(),
+        &StorageModifyRequest::default().clear_backup_rule(),
+    )
+    .await
+    .unwrap();
+    assert!(cleared.backup_rule.is_none());
+    assert_eq!(cleared.title, "Worlds");
+    let err = storage::modify(
+        &ctx,
+        created.uuid.clone(),
+        &StorageModifyRequest::default().size(10),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::SizeInvalid));
+
+    let copy = storage::clone(
+        &ctx,
+        created.uuid.clone(),
+        &StorageCloneRequest::new(String::from("de-fra1"), String::from("Worlds copy")),
+    )
+    .await
+    .unwrap();
+    assert_eq!(copy.size, 100);
+    assert_eq!(copy.zone, "de-fra1");
+    assert!(copy.servers.server.is_empty());
+
+    let detached = storage::detach(&ctx, server.uuid.clone(), String::from("virtio:1"))
+        .await
+        .unwrap();
+    assert_eq!(detached.storage_devices.storage_device.len(), 1);
+    storage::delete(&ctx, created.uuid).await.unwrap();
+    storage::delete(&ctx, copy.uuid).await.unwrap();
+}
+
+#[tokio::test]
+async fn finds_storages_left_behind() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let disk = server.storage_devices.storage_device[0].storage.clone();
+    assert!(storage::list_detached(&ctx).await.unwrap().is_empty());
+
+    server::stop(&ctx, server.uuid.clone(), &StopOptions::default())
+        .await
+        .unwrap();
+    server::delete(&ctx, server.uuid).await.unwrap();
+
+    let private = storage::list(&ctx, StorageFilter::Private).await.unwrap();
+    assert_eq!(private.len(), 1);
+    assert_eq!(private[0].uuid, disk);
+    assert_eq!(private[0].storage_type, StorageType::Normal);
+    assert_eq!(private[0].zone.as_deref(), Some("fi-hel1"));
+    assert!(private[0].created.is_some());
+    let templates = storage::list(&ctx, StorageFilter::Template).await.unwrap();
+    assert_eq!(templates.len(), 3);
+    let detached = storage::list_detached(&ctx).await.unwrap();
+    assert_eq!(detached.len(), 1);
+    assert_eq!(detached[0].uuid, disk);
+}
+
+#[tokio::test]
+async fn backs_up_and_restores_a_disk() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let disk = server.storage_devices.storage_device[0].storage.clone();
+
+    let backup = storage::create_backup(&ctx, disk.clone(), String::from("Before 1.17"))
+        .await
+        .unwrap();
+    assert_eq!(backup.storage_type, StorageType::Backup);
+    assert_eq!(backup.origin.as_ref(), Some(&disk));
+    let backups = storage::list_backups(&ctx, disk.clone()).await.unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_eq!(backups[0].title, "Before 1.17");
+    let details = storage::get(&ctx, disk.clone()).await.unwrap();
+    assert_eq!(details.backups.backup, vec![backup.uuid.clone()]);
+
+    let err = storage::restore_backup(&ctx, backup.uuid.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::ServerStateIllegal));
+    server::stop(&ctx, server.uuid.clone(), &StopOptions::default())
+        .await
+        .unwrap();
+    storage::restore_backup(&ctx, backup.uuid.clone())
+        .await
+        .unwrap();
+
+    let restored = storage::backup_to_new_storage(
+        &ctx,
+        backup.uuid.clone(),
+        &StorageCloneRequest::new(String::from("fi-hel1"), String::from("Rollback")),
+    )
+    .await
+    .unwrap();
+    assert_eq!(restored.storage_type, StorageType::Normal);
+    assert_eq!(restored.size, backup.size);
+
+    storage::delete(&ctx, backup.uuid).await.unwrap();
+    assert!(storage::list_backups(&ctx, disk).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn prunes_backups_by_retention_policy() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let disk = storage::create(
+        &ctx,
+        &StorageCreateRequest::new(String::from("fi-hel1"), 10, String::from("World")),
+    )
+    .await
+    .unwrap()
+    .uuid;
+    let other = storage::create(
+        &ctx,
+        &StorageCreateRequest::new(String::from("fi-hel1"), 10, String::from("Logs")),
+    )
+    .await
+    .unwrap()
+    .uuid;
+    // Nightly backups of the world for 60 days from Monday 2021-03-01, and a
+    // single one of the logs.
+    const DAY: u64 = 24 * 60 * 60;
+    let start = 1_614_556_800 + 4 * 60 * 60;
+    for day in 0..60 {
+        mock.state()
+            .insert_backup(&disk, &format!("Night {}", day), start + day * DAY);
+    }
+    let logs = mock.state().insert_backup(&other, "Logs", start);
+
+    let policy = RetentionPolicy::new(7, 4, 3).dry_run(true);
+    let report = backups::prune(&ctx, &policy).await.unwrap();
+    assert!(report.dry_run);
+    // The last 7 nights reach back to Friday, so the 4 weeks add only the
+    // Sundays of the 2 weeks before. The 3 months add the last night of
+    // March. Plus the one backup of the logs.
+    assert_eq!(report.kept.len(), 7 + 2 + 1 + 1);
+    assert_eq!(report.deleted.len(), 60 - 10);
+    assert!(report.kept.iter().any(|backup| backup.uuid == logs));
+    assert_eq!(
+        storage::list_backups(&ctx, disk.clone())
+            .await
+            .unwrap()
+            .len(),
+        60
+    );
+
+    let report = backups::prune(&ctx, &policy.dry_run(false)).await.unwrap();
+    assert_eq!(report.deleted.len(), 60 - 10);
+    let left = storage::list_backups(&ctx, disk.clone()).await.unwrap();
+    assert_eq!(left.len(), 10);
+    let details = storage::get(&ctx, disk).await.unwrap();
+    assert_eq!(details.backups.backup.len(), 10);
+}
+
+#[tokio::test]
+async fn reports_the_backups_pruned_before_a_failure() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let disk = storage::create(
+        &ctx,
+        &StorageCreateRequest::new(String::from("fi-hel1"), 10, String::from("World")),
+    )
+    .await
+    .unwrap()
+    .uuid;
+    const DAY: u64 = 24 * 60 * 60;
+    let start = 1_614_556_800;
+    for day in 0..5 {
+        mock.state()
+            .insert_backup(&disk, &format!("Night {}", day), start + day * DAY);
+    }
+    let policy = RetentionPolicy::new(1, 0, 0).dry_run(true);
+    let planned = backups::prune(&ctx, &policy).await.unwrap().deleted;
+    assert_eq!(planned.len(), 4);
+    mock.fail(
+        Failure::new(409, "STORAGE_STATE_ILLEGAL")
+            .method("DELETE")
+            .path(&format!("/storage/{}", planned[2].uuid)),
+    );
+
+    let report = backups::prune(&ctx, &policy.dry_run(false)).await.unwrap();
+
+    let uuids = |backups: &[cloudup::storage::Storage]| -> Vec<String> {
+        backups.iter().map(|backup| backup.uuid.clone()).collect()
+    };
+    assert_eq!(uuids(&report.deleted), uuids(&planned[..2]));
+    assert_eq!(uuids(&report.left), uuids(&planned[2..]));
+    assert_eq!(
+        report.error.unwrap().code(),
+        Some(&ErrorCode::StorageStateIllegal)
+    );
+    assert_eq!(storage::list_backups(&ctx, disk).await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn bakes_a_template_of_a_server() {
+    let mock = MockUpCloud::start().await;
+    mock.set_transition_delay(Duration::from_millis(100));
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let disk = server.storage_devices.storage_device[0].storage.clone();
+    let options = BakeOptions::new(
+        cloudup::WaitOptions::new(Duration::from_secs(5)).poll_interval(Duration::from_millis(20)),
+    )
+    .restart(true);
+
+    let baked = server::bake_template(
+        &ctx,
+        server.uuid.clone(),
+        String::from("Minecraft 1.17"),
+        &options,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(baked.storage_type, StorageType::Template);
+    assert_eq!(baked.state, StorageState::Online);
+    assert_eq!(baked.origin.as_ref(), Some(&disk));
+    let templates = storage::list(&ctx, StorageFilter::Template).await.unwrap();
+    assert!(templates.iter().any(|template| template.uuid == baked.uuid));
+    let restarted = server::wait_for_state_with(
+        &ctx,
+        server.uuid,
+        ServerState::Started,
+        &options.wait,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(restarted.storage_devices.storage_device[0].storage, disk);
+    server::create(&ctx, &minecraft_server(baked.uuid))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn restarts_the_server_after_a_failed_bake() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let disk = server.storage_devices.storage_device[0].storage.clone();
+    let options = BakeOptions::new(
+        cloudup::WaitOptions::new(Duration::from_secs(5)).poll_interval(Duration::from_millis(20)),
+    )
+    .restart(true);
+    server::wait_for_state_with(
+        &ctx,
+        server.uuid.clone(),
+        ServerState::Started,
+        &options.wait,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+    mock.fail(
+        Failure::new(500, "INTERNAL_ERROR")
+            .method("POST")
+            .path(&format!("/storage/{}/templatize", disk)),
+    );
+
+    let err = server::bake_template(
+        &ctx,
+        server.uuid.clone(),
+        String::from("Minecraft 1.17"),
+        &options,
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+        err.status(),
+        Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+    server::wait_for_state_with(
+        &ctx,
+        server.uuid,
+        ServerState::Started,
+        &options.wait,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn resizes_the_disk_of_a_running_server() {
+    let mock = MockUpCloud::start().await;
+    mock.set_transition_delay(Duration::from_millis(100));
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let disk = server.storage_devices.storage_device[0].storage.clone();
+    let options = ResizeOptions::new(
+        cloudup::WaitOptions::new(Duration::from_secs(5)).poll_interval(Duration::from_millis(20)),
+    );
+    server::wait_for_state_with(
+        &ctx,
+        server.uuid.clone(),
+        ServerState::Started,
+        &options.wait,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+    let err = storage::resize_filesystem(&ctx, disk.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::ServerStateIllegal));
+
+    let report = storage::resize(&ctx, disk.clone(), 120, &options)
+        .await
+        .unwrap();
+
+    assert_eq!(report.storage.size, 120);
+    assert_eq!(report.storage.state, StorageState::Online);
+    assert_eq!(report.storage.backups.backup, vec![report.backup.clone()]);
+    let backup = storage::get(&ctx, report.backup.clone()).await.unwrap();
+    assert_eq!(backup.storage_type, StorageType::Backup);
+    assert_eq!(backup.origin, Some(disk));
+    assert_eq!(report.stopped, vec![server.uuid.clone()]);
+    server::wait_for_state_with(
+        &ctx,
+        server.uuid,
+        ServerState::Started,
+        &options.wait,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+    storage::delete(&ctx, report.backup).await.unwrap();
+}
+
+#[tokio::test]
+async fn restarts_servers_after_a_failed_resize() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let disk = server.storage_devices.storage_device[0].storage.clone();
+    let options = ResizeOptions::new(
+        cloudup::WaitOptions::new(Duration::from_secs(5)).poll_interval(Duration::from_millis(20)),
+    );
+    server::wait_for_state_with(
+        &ctx,
+        server.uuid.clone(),
+        ServerState::Started,
+        &options.wait,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+    mock.fail(
+        Failure::new(500, "INTERNAL_ERROR")
+            .method("POST")
+            .path(&format!("/storage/{}/resize", disk)),
+    );
+
+    let err = storage::resize(&ctx, disk.clone(), 120, &options)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.status(),
+        Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+    assert_eq!(storage::get(&ctx, disk).await.unwrap().size, 120);
+    server::wait_for_state_with(
+        &ctx,
+        server.uuid,
+        ServerState::Started,
+        &options.wait,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn restarts_every_server_when_one_fails_to_start() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let first = server::create(&ctx, &minecraft_server(template.clone()))
+        .await
+        .unwrap();
+    let second = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let disk = first.storage_devices.storage_device[0].storage.clone();
+    storage::attach(
+        &ctx,
+        second.uuid.clone(),
+        &StorageAttachment::cdrom(disk.clone()),
+    )
+    .await
+    .unwrap();
+    let options = ResizeOptions::new(
+        cloudup::WaitOptions::new(Duration::from_secs(5)).poll_interval(Duration::from_millis(20)),
+    );
+    mock.fail(
+        Failure::new(500, "INTERNAL_ERROR")
+            .method("POST")
+            .path(&format!("/server/{}/start", first.uuid)),
+    );
+
+    let err = storage::resize(&ctx, disk.clone(), 120, &options)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.status(),
+        Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+    assert_eq!(storage::get(&ctx, disk).await.unwrap().size, 120);
+    server::wait_for_state_with(
+        &ctx,
+        second.uuid,
+        ServerState::Started,
+        &options.wait,
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+    let first = server::get_server(&ctx, first.uuid).await.unwrap();
+    assert_eq!(first.state, ServerState::Stopped);
+}
+
+#[tokio::test]
+async fn manages_ip_addresses() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    assert_eq!(ip_addresses::list(&ctx).await.unwrap().len(), 2);
+
+    let public = ip_addresses::assign(
+        &ctx,
+        &IpAddressRequest::new(server.uuid.clone(), IpFamily::IPv4),
+    )
+    .await
+    .unwrap();
+    let private = ip_addresses::assign(
+        &ctx,
+        &IpAddressRequest::new(server.uuid.clone(), IpFamily::IPv4).access(AccessType::Private),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(public.access, AccessType::Public);
+    assert_eq!(public.server.as_ref(), Some(&server.uuid));
+    assert_eq!(public.zone.as_deref(), Some("fi-hel1"));
+    assert_eq!(private.access, AccessType::Utility);
+    assert_eq!(ip_addresses::list(&ctx).await.unwrap().len(), 4);
+    let named =
+        ip_addresses::set_ptr_record(&ctx, public.address.clone(), String::from("mc.example.com"))
+            .await
+            .unwrap();
+    assert_eq!(named.ptr_record.as_deref(), Some("mc.example.com"));
+    let fetched = ip_addresses::get(&ctx, public.address.clone())
+        .await
+        .unwrap();
+    assert_eq!(fetched.ptr_record.as_deref(), Some("mc.example.com"));
+    assert_eq!(fetched.mac, public.mac);
+
+    ip_addresses::release(&ctx, public.address.clone())
+        .await
+        .unwrap();
+    ip_addresses::release(&ctx, private.address).await.unwrap();
+    let err = ip_addresses::get(&ctx, public.address).await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::IpAddressNotFound));
+    assert_eq!(ip_addresses::list(&ctx).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn fails_over_a_floating_ip() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let old = server::create(&ctx, &minecraft_server(template.clone()))
+        .await
+        .unwrap();
+    let new = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let floating = ip_addresses::assign(
+        &ctx,
+        &IpAddressRequest::floating(String::from("fi-hel1"))
+            .mac(floating_ip::public_mac(&old).unwrap().to_string()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(floating.floating, Some(true));
+    assert_eq!(floating.server.as_ref(), Some(&old.uuid));
+    let spare = floating_ip::create(&ctx, String::from("fi-hel1"))
+        .await
+        .unwrap();
+    assert_eq!(spare.server, None);
+    assert_eq!(floating_ip::list(&ctx).await.unwrap().len(), 2);
+
+    let moved = floating_ip::failover(
+        &ctx,
+        floating.address.clone(),
+        old.uuid.clone(),
+        new.uuid.clone(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(moved.server.as_ref(), Some(&new.uuid));
+    assert_eq!(moved.mac.as_deref(), floating_ip::public_mac(&new));
+    let err = floating_ip::failover(
+        &ctx,
+        floating.address.clone(),
+        String::from("00000000-0000-4000-8000-ffffffffffff"),
+        old.uuid.clone(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::UnexpectedAttachment { actual: Some(ref actual), .. } if *actual == new.uuid
+    ));
+
+    // Rebuilding: the floating address outlives the server it was on.
+    server::stop(&ctx, new.uuid.clone(), &StopOptions::default())
+        .await
+        .unwrap();
+    server::delete(&ctx, new.uuid.clone()).await.unwrap();
+    let detached = ip_addresses::get(&ctx, floating.address.clone())
+        .await
+        .unwrap();
+    assert_eq!(detached.server, None);
+    let back = floating_ip::failover(&ctx, floating.address.clone(), new.uuid, old.uuid.clone())
+        .await
+        .unwrap();
+    assert_eq!(back.server.as_ref(), Some(&old.uuid));
+
+    let parked = floating_ip::detach(&ctx, floating.address).await.unwrap();
+    assert_eq!(parked.server, None);
+    let err = floating_ip::attach(&ctx, spare.address, String::from("de:ad:be:ef:00:00"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+}
+
+#[tokio::test]
+async fn puts_servers_on_a_private_network() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let router = networks::create_router(&ctx, String::from("Backbone"))
+        .await
+        .unwrap();
+    let network = networks::create(
+        &ctx,
+        &NetworkCreateRequest::new(
+            String::from("Backend"),
+            String::from("fi-hel1"),
+            IpNetwork::new(String::from("172.16.0.0/24"), IpFamily::IPv4)
+                .gateway(String::from("172.16.0.1")),
+        )
+        .router(router.uuid.clone()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(network.router.as_ref(), Some(&router.uuid));
+    let attached = networks::get_router(&ctx, router.uuid.clone())
+        .await
+        .unwrap();
+    assert_eq!(attached.attached_networks.network[0].uuid, network.uuid);
+    assert_eq!(
+        networks::list_in_zone(&ctx, String::from("de-fra1"))
+            .await
+            .unwrap()
+            .len(),
+        0
+    );
+
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let private =
+        IpAddrTypePair::private(network.uuid.clone(), IpAddressSpecs::new(IpFamily::IPv4));
+    let err = server::interfaces::add(&ctx, server.uuid.clone(), &private)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::ServerStateIllegal));
+    server::stop(&ctx, server.uuid.clone(), &StopOptions::default())
+        .await
+        .unwrap();
+
+    let interface = server::interfaces::add(&ctx, server.uuid.clone(), &private)
+        .await
+        .unwrap();
+
+    assert_eq!(interface.index, 3);
+    assert_eq!(interface.interface_type, InterfaceType::Private);
+    assert!(interface.ip_addresses.ip_address[0]
+        .address
+        .starts_with("172.16.0."));
+    let joined = networks::get(&ctx, network.uuid.clone()).await.unwrap();
+    assert_eq!(joined.servers.server[0].uuid, server.uuid);
+    let err = networks::delete(&ctx, network.uuid.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::CONFLICT));
+
+    let fixed = server::interfaces::modify(
+        &ctx,
+        server.uuid.clone(),
+        interface.index,
+        &IpAddrTypePair::private(
+            network.uuid.clone(),
+            IpAddressSpecs::new(IpFamily::IPv4).address(String::from("172.16.0.100")),
+        ),
+    )
+    .await
+    .unwrap();
+    assert_eq!(fixed.mac, interface.mac);
+    assert_eq!(fixed.ip_addresses.ip_address[0].address, "172.16.0.100");
+    server::interfaces::delete(&ctx, server.uuid.clone(), interface.index)
+        .await
+        .unwrap();
+    let detached = networks::detach_router(&ctx, network.uuid.clone())
+        .await
+        .unwrap();
+    assert_eq!(detached.router, None);
+    networks::delete_router(&ctx, router.uuid).await.unwrap();
+    networks::delete(&ctx, network.uuid.clone()).await.unwrap();
+    let err = networks::get(&ctx, network.uuid).await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::NetworkNotFound));
+}
+
+#[tokio::test]
+async fn manages_server_interfaces() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    server::stop(&ctx, server.uuid.clone(), &StopOptions::default())
+        .await
+        .unwrap();
+    let interfaces = server::interfaces::list(&ctx, server.uuid.clone())
+        .await
+        .unwrap();
+    assert_eq!(interfaces.len(), 2);
+    assert_eq!(
+        interfaces[1].ip_addresses.ip_address[0].family,
+        IpFamily::IPv6
+    );
+
+    let utility = server::interfaces::add(
+        &ctx,
+        server.uuid.clone(),
+        &IpAddrTypePair::utility().index(5).bootable(true),
+    )
+    .await
+    .unwrap();
+    assert_eq!(utility.index, 5);
+    assert_eq!(utility.interface_type, InterfaceType::Utility);
+    assert_eq!(utility.bootable, Some(true));
+
+    let public = &interfaces[0];
+    let unfiltered = server::interfaces::modify(
+        &ctx,
+        server.uuid.clone(),
+        public.index,
+        &IpAddrTypePair::public(IpFamily::IPv4).source_ip_filtering(false),
+    )
+    .await
+    .unwrap();
+    assert_eq!(unfiltered.source_ip_filtering, Some(false));
+    assert_eq!(
+        unfiltered.ip_addresses.ip_address[0].address,
+        public.ip_addresses.ip_address[0].address
+    );
+    let fetched = server::interfaces::get(&ctx, server.uuid.clone(), public.index)
+        .await
+        .unwrap();
+    assert_eq!(fetched.mac, public.mac);
+    assert_eq!(fetched.source_ip_filtering, Some(false));
+
+    server::interfaces::delete(&ctx, server.uuid.clone(), utility.index)
+        .await
+        .unwrap();
+    let err = server::interfaces::get(&ctx, server.uuid.clone(), utility.index)
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+    let details = server::get_server(&ctx, server.uuid).await.unwrap();
+    assert_eq!(details.interfaces().len(), 2);
+}
+
+#[tokio::test]
+async fn boots_a_rescue_cdrom() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(
+        &ctx,
+        &minecraft_server(template.clone()).boot_order(BootOrder::disk_only()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(server.boot_order, BootOrder::disk_only());
+    let cdroms = storage::get_cdroms(&ctx).await.unwrap();
+    assert_eq!(cdroms.len(), 1);
+    let rescue = cdroms[0].uuid.clone();
+
+    let loaded = server::load_cdrom(&ctx, server.uuid.clone(), rescue.clone())
+        .await
+        .unwrap();
+    let cdrom = loaded
+        .storage_devices
+        .storage_device
+        .iter()
+        .find(|device| device.storage_type == "cdrom")
+        .unwrap();
+    assert_eq!(cdrom.storage, rescue);
+    let err = server::load_cdrom(&ctx, server.uuid.clone(), template)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.code(),
+        Some(&ErrorCode::Other(String::from("STORAGE_TYPE_ILLEGAL")))
+    );
+
+    let modified = server::set_boot_order(&ctx, server.uuid.clone(), &BootOrder::cdrom_first())
+        .await
+        .unwrap();
+    assert_eq!(modified.boot_order, BootOrder::cdrom_first());
+
+    let ejected = server::eject_cdrom(&ctx, server.uuid.clone())
+        .await
+        .unwrap();
+    assert_eq!(ejected.storage_devices.storage_device.len(), 1);
+    let err = server::eject_cdrom(&ctx, server.uuid).await.unwrap_err();
+    assert_eq!(
+        err.code(),
+        Some(&ErrorCode::Other(String::from("CDROM_DEVICE_NOT_FOUND")))
+    );
+}
+
+/// Writes `data` to a file in the temporary directory, named after the test.
+fn image_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cloudup-{}-{}", std::process::id(), name));
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn imports_images() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let disk = storage::create(
+        &ctx,
+        &StorageCreateRequest::new(String::from("fi-hel1"), 10, String::from("Image")),
+    )
+    .await
+    .unwrap()
+    .uuid;
+    let image: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+    let path = image_file("world.img", &image);
+
+    let progress = Arc::new(Mutex::new(vec![]));
+    let reported = progress.clone();
+    let options = ImportOptions::new(
+        cloudup::WaitOptions::new(Duration::from_secs(5)).poll_interval(Duration::from_millis(20)),
+    )
+    .progress(move |sent, total| reported.lock().unwrap().push((sent, total)));
+    let imported = storage::import(
+        &ctx,
+        disk.clone(),
+        ImportSource::File(path.clone()),
+        &options,
+    )
+    .await
+    .unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(imported.state, ImportState::Completed);
+    assert_eq!(imported.written_bytes, image.len() as u64);
+    assert_eq!(imported.client_content_type, "application/octet-stream");
+    let reports = progress.lock().unwrap().clone();
+    assert!(reports.len() > 1);
+    assert_eq!(
+        reports.last(),
+        Some(&(image.len() as u64, Some(image.len() as u64)))
+    );
+
+    let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+    gzip.write_all(&image).unwrap();
+    let path = image_file("world.img.gz", &gzip.finish().unwrap());
+    let imported = storage::import(
+        &ctx,
+        disk.clone(),
+        ImportSource::File(path.clone()),
+        &options,
+    )
+    .await
+    .unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(imported.client_content_type, "application/gzip");
+    assert_eq!(imported.written_bytes, image.len() as u64);
+
+    let mut xz = vec![];
+    lzma_rs::xz_compress(&mut &image[..], &mut xz).unwrap();
+    let path = image_file("world.img.xz", &xz);
+    let imported = storage::import(
+        &ctx,
+        disk.clone(),
+        ImportSource::File(path.clone()),
+        &options,
+    )
+    .await
+    .unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(imported.client_content_type, "application/x-xz");
+    assert_eq!(imported.written_bytes, image.len() as u64);
+
+    let path = image_file("broken.img.gz", b"not really gzip");
+    let err = storage::import(
+        &ctx,
+        disk.clone(),
+        ImportSource::File(path.clone()),
+        &options,
+    )
+    .await
+    .unwrap_err();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(
+        err.code(),
+        Some(&ErrorCode::Other(String::from("DECOMPRESSION_FAILED")))
+    );
+
+    let imported = storage::import(
+        &ctx,
+        disk.clone(),
+        ImportSource::Url(String::from("https://example.com/world.img")),
+        &options,
+    )
+    .await
+    .unwrap();
+    assert_eq!(imported.source, ImportMethod::HttpImport);
+
+    let started = storage::start_import(&ctx, disk.clone(), ImportMethod::DirectUpload, None)
+        .await
+        .unwrap();
+    assert_eq!(started.state, ImportState::Prepared);
+    let cancelled = storage::cancel_import(&ctx, disk).await.unwrap();
+    assert_eq!(cancelled.state, ImportState::Cancelled);
+}
+
+#[tokio::test]
+async fn cancels_the_import_when_the_upload_fails() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let disk = storage::create(
+        &ctx,
+        &StorageCreateRequest::new(String::from("fi-hel1"), 10, String::from("Image")),
+    )
+    .await
+    .unwrap()
+    .uuid;
+    mock.fail(Failure::new(503, "UPLOAD_FAILED").path("/uploader/session/"));
+    let path = image_file("interrupted.img", &[1; 4096]);
+    let options = ImportOptions::new(cloudup::WaitOptions::new(Duration::from_secs(5)));
+
+    let err = storage::import(
+        &ctx,
+        disk.clone(),
+        ImportSource::File(path.clone()),
+        &options,
+    )
+    .await
+    .unwrap_err();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        err.code(),
+        Some(&ErrorCode::Other(String::from("UPLOAD_FAILED")))
+    );
+    let cancel_path = format!("/storage/{}/import/cancel", disk);
+    assert!(mock
+        .received_requests()
+        .iter()
+        .any(|request| request.method == "POST" && request.path == cancel_path));
+    let import = storage::get_import(&ctx, disk).await.unwrap();
+    assert_eq!(import.state, ImportState::Cancelled);
+}
+
+#[cfg(not(feature = "zstd"))]
+#[tokio::test]
+async fn refuses_zstd_images_without_the_feature() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let path = image_file("refused.img.zst", b"");
+    let options = ImportOptions::new(cloudup::WaitOptions::new(Duration::from_secs(5)));
+
+    let err = storage::import(
+        &ctx,
+        String::from("any"),
+        ImportSource::File(path.clone()),
+        &options,
+    )
+    .await
+    .unwrap_err();
+    std::fs::remove_file(path).unwrap();
+
+    assert!(matches!(err, Error::Io(_)));
+    assert!(mock.received_requests().is_empty());
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn unpacks_zstd_images() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let disk = storage::create(
+        &ctx,
+        &StorageCreateRequest::new(String::from("fi-hel1"), 10, String::from("Image")),
+    )
+    .await
+    .unwrap()
+    .uuid;
+    let image = vec![7u8; 2_000_000];
+    let path = image_file("packed.img.zst", &zstd::encode_all(&image[..], 3).unwrap());
+    let options = ImportOptions::new(
+        cloudup::WaitOptions::new(Duration::from_secs(5)).poll_interval(Duration::from_millis(20)),
+    );
+
+    let imported = storage::import(&ctx, disk, ImportSource::File(path.clone()), &options)
+        .await
+        .unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(imported.written_bytes, image.len() as u64);
+}
+
+#[tokio::test]
+async fn refuses_to_delete_a_running_server() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let created = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+
+    let err = server::delete(&ctx, created.uuid).await.unwrap_err();
+
+    assert_eq!(err.code(), Some(&ErrorCode::ServerStateIllegal));
+}
+
+#[tokio::test]
+async fn checks_credentials() {
+    let mock = MockUpCloud::start().await;
+    let ctx = Context::builder(String::from("someone"), String::from("else"))
+        .base_url(mock.uri())
+        .build()
+        .unwrap();
+
+    let err = cloudup::plan::get_plans(&ctx).await.unwrap_err();
+
+    assert_eq!(err.code(), Some(&ErrorCode::AuthenticationFailed));
+}
+
+#[tokio::test]
+async fn injected_failures() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    mock.fail(
+        Failure::new(402, "INSUFFICIENT_CREDITS")
+            .method("POST")
+            .path("/server"),
+    );
+
+    let err = server::create(&ctx, &minecraft_server(template.clone()))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::InsufficientCredits));
+
+    server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn retries_injected_rate_limits() {
+    let mock = MockUpCloud::start().await;
+    mock.fail(Failure::new(429, "TOO_MANY_REQUESTS").times(2));
+    let ctx = Context::builder(mock.username(), mock.password())
+        .base_url(mock.uri())
+        .retry_policy(RetryPolicy::default().initial_backoff(Duration::from_millis(10)))
+        .build()
+        .unwrap();
+
+    cloudup::plan::get_plans(&ctx).await.unwrap();
+
+    assert_eq!(mock.received_requests().len(), 3);
+}
+
+#[tokio::test]
+async fn latency_runs_into_timeouts() {
+    let mock = MockUpCloud::start().await;
+    mock.set_latency(Duration::from_millis(500));
+    let ctx = Context::builder(mock.username(), mock.password())
+        .base_url(mock.uri())
+        .timeout(Duration::from_millis(100))
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap();
+
+    let err = cloudup::plan::get_plans(&ctx).await.unwrap_err();
+
+    match err {
+        Error::Transport(inner) => assert!(inner.is_timeout()),
+        other => panic!("Expected a timeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn manages_firewall_rules() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let minecraft = FirewallRule::new(
+        FirewallDirection::In,
+        FirewallAction::Accept,
+        IpFamily::IPv4,
+    )
+    .protocol(FirewallProtocol::Tcp)
+    .destination_port(25565, 25565)
+    .comment(String::from("Minecraft"));
+    let drop_rest = FirewallRule::new(FirewallDirection::In, FirewallAction::Drop, IpFamily::IPv4);
+
+    firewall::replace_all(
+        &ctx,
+        server.uuid.clone(),
+        &[minecraft.clone(), drop_rest.clone()],
+    )
+    .await
+    .unwrap();
+    let rules = firewall::list(&ctx, server.uuid.clone()).await.unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0], minecraft.clone().position(1));
+    assert_eq!(rules[1], drop_rest.position(2));
+
+    let ssh = FirewallRule::new(
+        FirewallDirection::In,
+        FirewallAction::Accept,
+        IpFamily::IPv4,
+    )
+    .protocol(FirewallProtocol::Tcp)
+    .destination_port(22, 22)
+    .source_address(String::from("192.0.2.1"), String::from("192.0.2.255"))
+    .position(1);
+    let created = firewall::create(&ctx, server.uuid.clone(), &ssh)
+        .await
+        .unwrap();
+    assert_eq!(created, ssh);
+    let moved = firewall::get(&ctx, server.uuid.clone(), 2).await.unwrap();
+    assert_eq!(moved.comment.as_deref(), Some("Minecraft"));
+
+    firewall::delete(&ctx, server.uuid.clone(), 1)
+        .await
+        .unwrap();
+    let rules = firewall::list(&ctx, server.uuid.clone()).await.unwrap();
+    assert_eq!(rules[0], minecraft.position(1));
+    let err = firewall::get(&ctx, server.uuid.clone(), 3)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::FirewallRuleNotFound));
+
+    let details = firewall::set_enabled(&ctx, server.uuid.clone(), true)
+        .await
+        .unwrap();
+    assert!(details.firewall);
+    let details = firewall::set_enabled(&ctx, server.uuid, false)
+        .await
+        .unwrap();
+    assert!(!details.firewall);
+}
+
+#[tokio::test]
+async fn does_not_retry_deleting_a_firewall_rule() {
+    let mock = MockUpCloud::start().await;
+    let ctx = Context::builder(mock.username(), mock.password())
+        .base_url(mock.uri())
+        .timeout(Duration::from_millis(200))
+        .retry_policy(RetryPolicy::default().initial_backoff(Duration::from_millis(10)))
+        .build()
+        .unwrap();
+    let template = mock.state().template("CentOS 8").unwrap().uuid.clone();
+    let server = server::create(&ctx, &minecraft_server(template))
+        .await
+        .unwrap();
+    let rules: Vec<FirewallRule> = [22, 80, 25565]
+        .iter()
+        .map(|&port| {
+            FirewallRule::new(
+                FirewallDirection::In,
+                FirewallAction::Accept,
+                IpFamily::IPv4,
+            )
+            .protocol(FirewallProtocol::Tcp)
+            .destination_port(port, port)
+        })
+        .collect();
+    firewall::replace_all(&ctx, server.uuid.clone(), &rules)
+        .await
+        .unwrap();
+    mock.fail(
+        Failure::new(503, "SERVICE_UNAVAILABLE")
+            .method("DELETE")
+            .path("/server")
+            .after_applying()
+            .delay(Duration::from_millis(500)),
+    );
+
+    let err = firewall::delete(&ctx, server.uuid.clone(), 1)
+        .await
+        .unwrap_err();
+
+    match err {
+        Error::Transport(inner) => assert!(inner.is_timeout()),
+        other => panic!("Expected a timeout, got {:?}", other),
+    }
+    let left = firewall::list(&ctx, server.uuid).await.unwrap();
+    let ports: Vec<Option<u16>> = left
+        .iter()
+        .map(|rule| rule.destination_port_start)
+        .collect();
+    assert_eq!(ports, vec![Some(80), Some(25565)]);
+}
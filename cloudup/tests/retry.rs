@@ -0,0 +1,244 @@
+use std::time::{Duration, Instant};
+
+use cloudup::server::{BootOrder, StopOptions};
+use cloudup::{Context, Error, RetryPolicy};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+
+use common::{server_json, SERVER_UUID};
+
+const STORAGE_UUID: &str = "0174b1f3-0bdf-4d7e-9b33-2e2e1e8fbf5c";
+
+fn quick_policy() -> RetryPolicy {
+    RetryPolicy::default()
+        .initial_backoff(Duration::from_millis(10))
+        .jitter(false)
+}
+
+fn context(server: &MockServer, policy: RetryPolicy) -> Context {
+    Context::builder(String::from("user"), String::from("pass"))
+        .base_url(server.uri())
+        .retry_policy(policy)
+        .build()
+        .unwrap()
+}
+
+fn plans_response() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "plans": { "plan": [] } }))
+}
+
+#[tokio::test]
+async fn retries_get_until_it_succeeds() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/1.3/plan"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.3/plan"))
+        .respond_with(plans_response())
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let ctx = context(&server, quick_policy());
+    let plans = cloudup::plan::get_plans(&ctx).await.unwrap();
+
+    assert!(plans.is_empty());
+}
+
+#[tokio::test]
+async fn gives_up_after_max_attempts() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/1.3/plan"))
+        .respond_with(ResponseTemplate::new(429))
+        .expect(4)
+        .mount(&server)
+        .await;
+
+    let ctx = context(&server, quick_policy().max_attempts(4));
+    let err = cloudup::plan::get_plans(&ctx).await.unwrap_err();
+
+    assert_eq!(err.status(), Some(reqwest::StatusCode::TOO_MANY_REQUESTS));
+}
+
+#[tokio::test]
+async fn honors_retry_after() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/1.3/plan"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.3/plan"))
+        .respond_with(plans_response())
+        .mount(&server)
+        .await;
+
+    let ctx = context(&server, quick_policy());
+    let started = Instant::now();
+    cloudup::plan::get_plans(&ctx).await.unwrap();
+
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn caps_retry_after_at_the_max_backoff() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/1.3/plan"))
+        .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "3600"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/1.3/plan"))
+        .respond_with(plans_response())
+        .mount(&server)
+        .await;
+
+    let ctx = context(
+        &server,
+        quick_policy().max_backoff(Duration::from_millis(50)),
+    );
+    let started = Instant::now();
+    cloudup::plan::get_plans(&ctx).await.unwrap();
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn retries_deletes() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path(format!("/1.3/storage/{}", STORAGE_UUID)))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path(format!("/1.3/storage/{}", STORAGE_UUID)))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let ctx = context(&server, quick_policy());
+    cloudup::storage::delete(&ctx, String::from(STORAGE_UUID))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn does_not_retry_posts_by_default() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(format!("/1.3/server/{}/stop", SERVER_UUID)))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let ctx = context(&server, quick_policy());
+    let err = cloudup::server::stop(&ctx, String::from(SERVER_UUID), &StopOptions::default())
+        .await
+        .unwrap_err();
+
+    assert_eq!(err.status(), Some(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+}
+
+#[tokio::test]
+async fn does_not_retry_puts_by_default() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path(format!("/1.3/server/{}", SERVER_UUID)))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let ctx = context(&server, quick_policy());
+    let err =
+        cloudup::server::set_boot_order(&ctx, String::from(SERVER_UUID), &BootOrder::disk_only())
+            .await
+            .unwrap_err();
+
+    assert_eq!(err.status(), Some(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+}
+
+#[tokio::test]
+async fn retries_posts_when_asked_to() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(format!("/1.3/server/{}/stop", SERVER_UUID)))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(format!("/1.3/server/{}/stop", SERVER_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(server_json("maintenance")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let ctx = context(&server, quick_policy().retry_posts(true));
+    cloudup::server::stop(&ctx, String::from(SERVER_UUID), &StopOptions::default())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn does_not_retry_client_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/1.3/plan"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": {
+                "error_code": "AUTHENTICATION_FAILED",
+                "error_message": "Authentication failed using the given username and password."
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let ctx = context(&server, quick_policy());
+    let err = cloudup::plan::get_plans(&ctx).await.unwrap_err();
+
+    assert!(matches!(err, Error::Api(_)));
+    assert_eq!(
+        err.code(),
+        Some(&cloudup::error::ErrorCode::AuthenticationFailed)
+    );
+}
+
+#[tokio::test]
+async fn retries_connection_errors() {
+    // Grab a free port and close it again so that nothing is listening there.
+    let address = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let ctx = Context::builder(String::from("user"), String::from("pass"))
+        .base_url(format!("http://{}", address))
+        .retry_policy(quick_policy().initial_backoff(Duration::from_millis(100)))
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    let err = cloudup::plan::get_plans(&ctx).await.unwrap_err();
+
+    assert!(matches!(err, Error::Transport(_)));
+    // Two backoffs: 100ms and 200ms.
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}
@@ -0,0 +1,152 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use cloudup::server::{self, ServerState};
+use cloudup::{Context, Error, RetryPolicy, WaitOptions};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+mod common;
+
+use common::{server_json, SERVER_UUID};
+
+/// Plays back a server that is `started`, goes into `maintenance` while
+/// shutting down and ends up `stopped`. The clock starts at the first request.
+struct StoppingServer {
+    since: Mutex<Option<Instant>>,
+}
+
+impl Respond for StoppingServer {
+    fn respond(&self, _: &Request) -> ResponseTemplate {
+        let since = *self.since.lock().unwrap().get_or_insert_with(Instant::now);
+        let state = match since.elapsed().as_millis() {
+            0..=99 => "started",
+            100..=199 => "maintenance",
+            _ => "stopped",
+        };
+        ResponseTemplate::new(200).set_body_json(server_json(state))
+    }
+}
+
+async fn stopping_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/1.3/server/{}", SERVER_UUID)))
+        .respond_with(StoppingServer {
+            since: Mutex::new(None),
+        })
+        .mount(&server)
+        .await;
+    server
+}
+
+fn context(server: &MockServer) -> Context {
+    Context::builder(String::from("user"), String::from("pass"))
+        .base_url(server.uri())
+        .retry_policy(RetryPolicy::none())
+        .build()
+        .unwrap()
+}
+
+fn options(timeout: Duration) -> WaitOptions {
+    WaitOptions::new(timeout).poll_interval(Duration::from_millis(20))
+}
+
+#[tokio::test]
+async fn waits_until_the_state_is_reached() {
+    let mock = stopping_server().await;
+    let ctx = context(&mock);
+
+    let details = server::wait_for_state_with(
+        &ctx,
+        String::from(SERVER_UUID),
+        ServerState::Stopped,
+        &options(Duration::from_secs(5)),
+        std::future::pending(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(details.state, ServerState::Stopped);
+    assert!(mock.received_requests().await.unwrap().len() > 2);
+}
+
+#[tokio::test]
+async fn returns_at_once_when_already_there() {
+    let mock = stopping_server().await;
+    let ctx = context(&mock);
+
+    server::wait_for_state(
+        &ctx,
+        String::from(SERVER_UUID),
+        ServerState::Started,
+        Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(mock.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn times_out() {
+    let mock = stopping_server().await;
+    let ctx = context(&mock);
+
+    let err = server::wait_for_state_with(
+        &ctx,
+        String::from(SERVER_UUID),
+        ServerState::Stopped,
+        &options(Duration::from_millis(50)),
+        std::future::pending(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(err, Error::Timeout(_)));
+}
+
+#[tokio::test]
+async fn can_be_cancelled() {
+    let mock = stopping_server().await;
+    let ctx = context(&mock);
+
+    let err = server::wait_for_state_with(
+        &ctx,
+        String::from(SERVER_UUID),
+        ServerState::Error,
+        &options(Duration::from_secs(5)),
+        tokio::time::sleep(Duration::from_millis(150)),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(err, Error::Cancelled));
+}
+
+#[tokio::test]
+async fn passes_api_errors_through() {
+    let mock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "error": {
+                "error_code": "SERVER_NOT_FOUND",
+                "error_message": "The server does not exist."
+            }
+        })))
+        .mount(&mock)
+        .await;
+    let ctx = context(&mock);
+
+    let err = server::wait_for_state(
+        &ctx,
+        String::from(SERVER_UUID),
+        ServerState::Stopped,
+        Duration::from_secs(5),
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(err.code(), Some(&cloudup::error::ErrorCode::ServerNotFound));
+}
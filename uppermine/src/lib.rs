@@ -0,0 +1,105 @@
+use std::fmt;
+
+use cloudup::server::{
+    AccessType, IpAddrTypePair, IpFamily, LoginUser, ServerCreateRequest, ServerDetails,
+    StorageDevice,
+};
+use cloudup::storage::StorageTier;
+
+/// Installs Java and opens the Minecraft port on the first boot.
+pub const USER_DATA: &str =
+    "dnf install tmux tar java-1.8.0-openjdk -y && firewall-cmd --add-port 25565/tcp";
+
+/// What kind of a Minecraft server to set up and where.
+pub struct Config {
+    pub zone: String,
+    pub server_name: String,
+    pub username: String,
+    pub ssh_pub: String,
+    pub plan: String,
+    pub template: String,
+}
+
+impl Config {
+    pub fn new(zone: String, server_name: String, username: String, ssh_pub: String) -> Config {
+        Config {
+            zone,
+            server_name,
+            username,
+            ssh_pub,
+            plan: String::from("2xCPU-4GB"),
+            template: String::from("CentOS 8"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Api(cloudup::Error),
+    PlanNotFound(String),
+    TemplateNotFound(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Api(err) => write!(f, "{}", err),
+            Error::PlanNotFound(plan) => write!(f, "Desired plan {} was not found!", plan),
+            Error::TemplateNotFound(template) => write!(f, "{} was not found!", template),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<cloudup::Error> for Error {
+    fn from(err: cloudup::Error) -> Error {
+        Error::Api(err)
+    }
+}
+
+/// Creates a server ready to run Minecraft, returning it as it was booted.
+pub async fn provision(ctx: &cloudup::Context, config: &Config) -> Result<ServerDetails, Error> {
+    let plans = cloudup::plan::get_plans(ctx).await?;
+    let desired_plan = plans
+        .iter()
+        .find(|x| x.name == config.plan)
+        .ok_or_else(|| Error::PlanNotFound(config.plan.clone()))?;
+
+    let templates = cloudup::storage::get_templates(ctx).await?;
+    let template = templates
+        .iter()
+        .find(|x| x.title == config.template)
+        .ok_or_else(|| Error::TemplateNotFound(config.template.clone()))?;
+
+    let request = ServerCreateRequest::new(
+        config.zone.clone(),
+        config.server_name.clone(),
+        config.server_name.clone(),
+    )
+    .plan(desired_plan.name.clone())
+    .storage_device(
+        StorageDevice::clone_from(template.uuid.clone(), desired_plan.storage_size)
+            .title(template.title.clone())
+            .tier(StorageTier::Maxiops),
+    )
+    .interface(IpAddrTypePair::public(IpFamily::IPv4))
+    .login_user(LoginUser::new(
+        config.username.clone(),
+        vec![config.ssh_pub.clone()],
+    ))
+    .user_data(String::from(USER_DATA));
+
+    Ok(cloudup::server::create(ctx, &request).await?)
+}
+
+/// The public addresses a server was given.
+pub fn public_addresses(server: &ServerDetails) -> Vec<String> {
+    server
+        .ip_addresses
+        .ip_address
+        .iter()
+        .filter(|x| x.access == AccessType::Public)
+        .map(|x| x.address.clone())
+        .collect()
+}
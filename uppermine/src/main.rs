@@ -32,62 +32,24 @@ async fn main() {
         Err(err) => panic!("Couldn't read SERVER_NAME ({})", err),
     };
 
-    let ctx = cloudup::Context::new("https://api.upcloud.com", uc_user, uc_pass);
-
-    let plans: Vec<cloudup::plan::Plan> = cloudup::plan::get_plans(&ctx)
-        .await
-        .expect("Fetching plans failed");
-
-    let desired_plan: &cloudup::plan::Plan = plans
-        .iter()
-        .find(|x| x.name == "2xCPU-4GB")
-        .expect("Desired plan was not found!");
-
-    let templates: Vec<cloudup::storage::Storage> = cloudup::storage::get_templates(&ctx)
-        .await
-        .expect("Fetching templates failed");
-
-    let centos: &cloudup::storage::Storage = templates
-        .iter()
-        .find(|x| x.title == "CentOS 8")
-        .expect("CentsOS 8 was not found!");
+    let ctx = cloudup::Context::builder(uc_user, uc_pass)
+        .build()
+        .expect("Couldn't set up the API client");
 
     let servers: Vec<cloudup::server::ServerBasicInfo> = cloudup::server::get_servers(&ctx)
         .await
         .expect("Fetching server list failed");
     println!("{:#?}", servers);
 
+    let config = uppermine::Config::new(uc_zone, server_name, username, ssh_pub);
 
-    let api_resp: Result<cloudup::server::CreateServerResponse, reqwest::Error> =
-        cloudup::server::create_from_template(
-            &ctx,
-            centos.uuid.clone(),
-            server_name.clone(),
-            server_name,
-            centos.title.clone(),
-            desired_plan.storage_size,
-            desired_plan.name.clone(),
-            uc_zone,
-            String::from(
-                "dnf install tmux tar java-1.8.0-openjdk -y && firewall-cmd --add-port 25565/tcp",
-            ),
-            username,
-            vec![ssh_pub]
-        )
-        .await;
-
-    match api_resp {
+    match uppermine::provision(&ctx, &config).await {
         Ok(res) => {
             println!("Booting... Server sent response: {:#?}", res);
-            let addresses: Vec<String> = res
-                .server
-                .ip_addresses
-                .ip_address
-                .into_iter()
-                .filter(|x| x.access == "public")
-                .map(|x| x.address)
-                .collect();
-            println!("The IPs allocated were {:?}", addresses);
+            println!(
+                "The IPs allocated were {:?}",
+                uppermine::public_addresses(&res)
+            );
         }
         Err(inner) => panic!("Server creation failed! Inner {:#?}", inner),
     }
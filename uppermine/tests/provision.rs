@@ -0,0 +1,53 @@
+use cloudup::server::ServerState;
+use cloudup_mock::MockUpCloud;
+
+fn config() -> uppermine::Config {
+    uppermine::Config::new(
+        String::from("fi-hel1"),
+        String::from("minecraft"),
+        String::from("steve"),
+        String::from("ssh-ed25519 AAAA steve@example.com"),
+    )
+}
+
+fn context(mock: &MockUpCloud) -> cloudup::Context {
+    cloudup::Context::builder(mock.username(), mock.password())
+        .base_url(mock.uri())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn provisions_a_minecraft_server() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+
+    let server = uppermine::provision(&ctx, &config()).await.unwrap();
+
+    assert_eq!(server.state, ServerState::Started);
+    assert_eq!(uppermine::public_addresses(&server).len(), 1);
+    let create = mock
+        .received_requests()
+        .into_iter()
+        .find(|request| request.method == "POST" && request.path == "/server")
+        .unwrap();
+    let body = create.body.unwrap();
+    assert_eq!(body["server"]["plan"], "2xCPU-4GB");
+    assert_eq!(body["server"]["user_data"], uppermine::USER_DATA);
+    assert_eq!(
+        body["server"]["storage_devices"]["storage_device"][0]["size"],
+        80
+    );
+}
+
+#[tokio::test]
+async fn fails_without_the_template() {
+    let mock = MockUpCloud::start().await;
+    let ctx = context(&mock);
+    let mut config = config();
+    config.template = String::from("Windows 95");
+
+    let err = uppermine::provision(&ctx, &config).await.unwrap_err();
+
+    assert!(matches!(err, uppermine::Error::TemplateNotFound(_)));
+}